@@ -1,3 +1,14 @@
+pub mod bitboard;
+pub mod book;
 pub mod chess_types;
+pub mod endgame;
+pub mod evaluation;
 pub mod game_engine;
-pub mod game_session;
\ No newline at end of file
+pub mod game_session;
+pub mod search;
+#[cfg(test)]
+mod test_support;
+pub mod uci;
+pub mod zobrist;
+#[cfg(feature = "wasm")]
+pub mod wasm;
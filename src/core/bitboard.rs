@@ -0,0 +1,280 @@
+use std::sync::OnceLock;
+
+use super::chess_types::*;
+use super::game_engine::{KING_QUEEN_MOVE_OFFSETS, KNIGHT_MOVE_OFFSETS, PAWN_CAPTURE_OFFSETS};
+
+// one bit per square, the bit index matches BoardLayer: from a1 as bit 0 to h8 as bit 63
+pub type Bitboard = u64;
+
+// the squares of each color: h1 is light, a1 is dark
+pub const LIGHT_SQUARES: Bitboard = 0x55aa_55aa_55aa_55aa;
+pub const DARK_SQUARES: Bitboard = !LIGHT_SQUARES;
+
+pub fn of(address: Address) -> Bitboard {
+    1 << address.to_index()
+}
+
+pub fn contains(bitboard: Bitboard, address: Address) -> bool {
+    bitboard & of(address) != 0
+}
+
+// set squares from a1 to h8
+pub fn addresses(mut bitboard: Bitboard) -> impl Iterator<Item = Address> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+
+        let index = bitboard.trailing_zeros() as u8;
+        bitboard &= bitboard - 1;
+        Address::from_index(index)
+    })
+}
+
+// the board cells together with a bitboard per piece type and color and the material
+// of each color, every change goes through `set` so all the views always agree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Squares {
+    cells: BoardLayer<Option<Piece>>,
+    pieces: [Bitboard; 12],
+    colors: [Bitboard; 2],
+    material: [u32; 2],
+}
+
+impl Default for Squares {
+    fn default() -> Self {
+        Self::from_layer([None; CELLS_COUNT as usize])
+    }
+}
+
+impl Squares {
+    pub fn from_layer(cells: BoardLayer<Option<Piece>>) -> Self {
+        let mut res = Squares {
+            cells,
+            pieces: [0; 12],
+            colors: [0; 2],
+            material: [0; 2],
+        };
+
+        for (index, cell) in cells.iter().enumerate() {
+            if let Some(piece) = cell {
+                res.toggle(index, *piece);
+                res.material[piece.color as usize] += Board::get_material(*piece);
+            }
+        }
+
+        res
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn cells(&self) -> &BoardLayer<Option<Piece>> {
+        &self.cells
+    }
+
+    pub fn get(&self, index: usize) -> &Option<Piece> {
+        &self.cells[index]
+    }
+
+    pub fn set(&mut self, index: usize, cell: Option<Piece>) {
+        if let Some(old) = self.cells[index] {
+            self.toggle(index, old);
+            self.material[old.color as usize] -= Board::get_material(old);
+        }
+        if let Some(new) = cell {
+            self.toggle(index, new);
+            self.material[new.color as usize] += Board::get_material(new);
+        }
+        self.cells[index] = cell;
+    }
+
+    pub fn pieces(&self, piece: Piece) -> Bitboard {
+        self.pieces[Self::piece_index(piece)]
+    }
+
+    pub fn color(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    pub fn material(&self, color: Color) -> u32 {
+        self.material[color as usize]
+    }
+
+    fn toggle(&mut self, index: usize, piece: Piece) {
+        let bit = 1 << index;
+        self.pieces[Self::piece_index(piece)] ^= bit;
+        self.colors[piece.color as usize] ^= bit;
+    }
+
+    fn piece_index(piece: Piece) -> usize {
+        piece.color as usize * PieceType::ALL.len() + piece.piece_type as usize
+    }
+}
+
+// squares a scalar piece or a pawn attacks from each square, computed once
+struct AttackTables {
+    knight: BoardLayer<Bitboard>,
+    king: BoardLayer<Bitboard>,
+    pawn: [BoardLayer<Bitboard>; 2],
+}
+
+impl AttackTables {
+    fn get() -> &'static AttackTables {
+        static TABLES: OnceLock<AttackTables> = OnceLock::new();
+        TABLES.get_or_init(|| {
+            // offsets are given for white, black ones are rotated by 180 degrees
+            let table = |offsets: &[(i8, i8)], color: Color| -> BoardLayer<Bitboard> {
+                let mut res = [0; CELLS_COUNT as usize];
+                for (address, attacks) in (0..CELLS_COUNT).filter_map(Address::from_index).zip(res.iter_mut()) {
+                    for offset in offsets {
+                        let offset = if color == Color::White { *offset } else { (-offset.0, -offset.1) };
+                        if let Some(target) = address.get_shifted(offset) {
+                            *attacks |= of(target);
+                        }
+                    }
+                }
+                res
+            };
+
+            AttackTables {
+                knight: table(KNIGHT_MOVE_OFFSETS, Color::White),
+                king: table(KING_QUEEN_MOVE_OFFSETS, Color::White),
+                pawn: [table(PAWN_CAPTURE_OFFSETS, Color::White), table(PAWN_CAPTURE_OFFSETS, Color::Black)],
+            }
+        })
+    }
+}
+
+pub fn knight_attacks(address: Address) -> Bitboard {
+    AttackTables::get().knight[address.to_index() as usize]
+}
+
+pub fn king_attacks(address: Address) -> Bitboard {
+    AttackTables::get().king[address.to_index() as usize]
+}
+
+// capture squares of a pawn of that color standing on the address
+pub fn pawn_attacks(address: Address, color: Color) -> Bitboard {
+    AttackTables::get().pawn[color as usize][address.to_index() as usize]
+}
+
+// rays along the offsets up to and including the first occupied square
+pub fn ray_attacks(address: Address, offsets: &[(i8, i8)], occupied: Bitboard) -> Bitboard {
+    let mut res = 0;
+
+    for offset in offsets {
+        for target in address.ray(*offset) {
+            res |= of(target);
+            if contains(occupied, target) {
+                break;
+            }
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::game_engine;
+
+    fn assert_consistent(board: &Board) {
+        let mut occupied = 0;
+
+        for color in [Color::White, Color::Black] {
+            for piece_type in PieceType::ALL {
+                let piece = Piece { piece_type, color };
+                for address in addresses(board.bitboard(piece)) {
+                    assert_eq!(*board.get_cell(address), Some(piece));
+                }
+                occupied |= board.bitboard(piece);
+            }
+        }
+
+        assert_eq!(occupied, board.occupied());
+        assert_eq!(board.occupancy(Color::White) & board.occupancy(Color::Black), 0);
+        let mut material = [0; 2];
+        for (address, piece) in board.iter_pieces() {
+            assert!(contains(board.bitboard(piece), address));
+            assert!(contains(board.occupancy(piece.color), address));
+            material[piece.color as usize] += Board::get_material(piece);
+        }
+
+        // promotions, captures and their undoing all keep the running material right
+        assert_eq!(board.material_count(Color::White), material[0]);
+        assert_eq!(board.material_count(Color::Black), material[1]);
+    }
+
+    #[test]
+    fn addresses_order() {
+        let bits = of(Address::parse("h8")) | of(Address::parse("a1")) | of(Address::parse("e4"));
+        let res = addresses(bits).collect::<Vec<_>>();
+        assert_eq!(res, vec![Address::parse("a1"), Address::parse("e4"), Address::parse("h8")]);
+        assert_eq!(addresses(0).count(), 0);
+    }
+
+    #[test]
+    fn square_colors() {
+        for address in (0..CELLS_COUNT).filter_map(Address::from_index) {
+            let mask = if address.get_color() == Color::White { LIGHT_SQUARES } else { DARK_SQUARES };
+            assert!(contains(mask, address), "{}", address);
+        }
+        assert_eq!(LIGHT_SQUARES.count_ones(), 32);
+    }
+
+    #[test]
+    fn attacks() {
+        assert_eq!(knight_attacks(Address::parse("a1")), of(Address::parse("b3")) | of(Address::parse("c2")));
+        assert_eq!(king_attacks(Address::parse("e4")).count_ones(), 8);
+        assert_eq!(pawn_attacks(Address::parse("a2"), Color::White), of(Address::parse("b3")));
+        assert_eq!(pawn_attacks(Address::parse("e7"), Color::Black), of(Address::parse("d6")) | of(Address::parse("f6")));
+
+        // the ray stops at the blocker but includes it
+        let occupied = of(Address::parse("a4"));
+        let rook = ray_attacks(Address::parse("a1"), game_engine::ROOK_MOVE_OFFSETS, occupied);
+        assert_eq!(rook.count_ones(), 3 + 7);
+        assert!(contains(rook, Address::parse("a4")));
+        assert!(!contains(rook, Address::parse("a5")));
+    }
+
+    #[test]
+    fn bitboards_follow_random_games() {
+        // xorshift so the games are reproducible
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..20 {
+            let mut board = Board::new();
+            assert_consistent(&board);
+
+            for _ in 0..120 {
+                let moves = game_engine::get_all_legal_moves_promoting(&board);
+                if moves.is_empty() {
+                    break;
+                }
+
+                let (from, to, promotion) = moves[next() as usize % moves.len()];
+                game_engine::make_move_unchecked_promoting(&mut board, from, to, promotion);
+                assert_consistent(&board);
+
+                // now and then take a couple of moves back
+                if next() % 8 == 0 {
+                    board.undo_move();
+                    board.undo_move();
+                    assert_consistent(&board);
+                    board.redo_move();
+                    assert_consistent(&board);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use super::chess_types::*;
+use super::game_engine;
+
+// line of the book text, counted from 1, and what is wrong with it
+#[derive(Debug, PartialEq, Eq)]
+pub enum BookError {
+    MissingSeparator(usize),
+    InvalidFen{line: usize, error: FenError},
+    InvalidMove{line: usize, text: String},
+    InvalidWeight{line: usize, text: String},
+}
+
+// book moves with their weights by position. the text has one position per line:
+//
+//   rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 => e2e4 40, d2d4 35, g1f3
+//
+// a move without a weight weighs 1, empty lines and lines starting with '#' are skipped.
+// positions are matched by their zobrist hash, so the FEN counters do not matter
+// and a position reached by another move order is found all the same
+#[derive(Default)]
+pub struct OpeningBook {
+    positions: HashMap<u64, Vec<(Move, u32)>>,
+}
+
+impl OpeningBook {
+    pub fn parse(text: &str) -> Result<OpeningBook, BookError> {
+        let mut res = OpeningBook::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (fen, moves) = line.split_once("=>").ok_or(BookError::MissingSeparator(line_number))?;
+            let board = Board::from_fen(fen.trim()).map_err(|error| BookError::InvalidFen { line: line_number, error })?;
+            let legal_moves = game_engine::get_all_legal_moves_detailed(&board);
+
+            let entries = res.positions.entry(board.zobrist_hash()).or_default();
+            for entry in moves.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let (mv, weight) = entry.split_once(char::is_whitespace).unwrap_or((entry, "1"));
+
+                let invalid_move = || BookError::InvalidMove { line: line_number, text: mv.to_string() };
+                let (from, to, promotion) = game_engine::parse_uci_move(mv).map_err(|_| invalid_move())?;
+                let mv = *legal_moves.iter()
+                    .find(|legal| legal.from == from && legal.to == to && legal.promotion() == promotion)
+                    .ok_or_else(invalid_move)?;
+
+                let weight = weight.trim().parse::<u32>()
+                    .map_err(|_| BookError::InvalidWeight { line: line_number, text: weight.trim().to_string() })?;
+
+                entries.push((mv, weight));
+            }
+        }
+
+        Ok(res)
+    }
+
+    // the book moves of the position with their weights, empty when it is not in the book
+    pub fn moves(&self, board: &Board) -> &[(Move, u32)] {
+        self.positions.get(&board.zobrist_hash()).map_or(&[], Vec::as_slice)
+    }
+
+    // a book move picked with a chance proportional to its weight,
+    // None when the position is not in the book or all its moves weigh 0
+    #[cfg(feature = "rand")]
+    pub fn probe(&self, board: &Board, rng: &mut impl rand::Rng) -> Option<Move> {
+        let moves = self.moves(board);
+        let total = moves.iter().map(|(_, weight)| weight).sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = rng.gen_range(0..total);
+        for (mv, weight) in moves {
+            if pick < *weight {
+                return Some(*mv);
+            }
+            pick -= weight;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static BOOK: &str = "\
+# main lines
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 => e2e4 40, d2d4 35, g1f3
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 => c7c5 3, e7e5 0
+
+4k3/P7/8/8/8/8/8/4K3 w - - 0 1 => a7a8n 1
+";
+
+    fn uci(board: &Board, mv: &str) -> Move {
+        let (from, to, promotion) = game_engine::parse_uci_move(mv).unwrap();
+        *game_engine::get_all_legal_moves_detailed(board).iter()
+            .find(|legal| legal.from == from && legal.to == to && legal.promotion() == promotion)
+            .unwrap()
+    }
+
+    #[test]
+    fn book_lookup() {
+        let book = OpeningBook::parse(BOOK).unwrap();
+
+        let board = Board::new();
+        let moves = book.moves(&board);
+        assert_eq!(moves, &[(uci(&board, "e2e4"), 40), (uci(&board, "d2d4"), 35), (uci(&board, "g1f3"), 1)]);
+        assert_eq!(moves[0].0.kind, MoveKind::DoublePawnPush);
+
+        // the counters differ from the book line but the position is the same
+        let mut board = Board::new();
+        board.apply_uci_moves(&["g1f3", "g8f6", "f3g1", "f6g8"]).unwrap();
+        assert_eq!(book.moves(&board).len(), 3);
+
+        let board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(book.moves(&board)[0].0.promotion(), Some(PieceType::Knight));
+
+        let mut board = Board::new();
+        board.apply_uci_moves(&["d2d4"]).unwrap();
+        assert!(book.moves(&board).is_empty());
+    }
+
+    #[test]
+    fn book_errors() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        assert_eq!(OpeningBook::parse(start).err(), Some(BookError::MissingSeparator(1)));
+        assert_eq!(
+            OpeningBook::parse("\n8/8 w - - 0 1 => e2e4").err(),
+            Some(BookError::InvalidFen { line: 2, error: FenError::WrongRankCount(2) })
+        );
+        assert_eq!(
+            OpeningBook::parse(&format!("{} => e2e5 3", start)).err(),
+            Some(BookError::InvalidMove { line: 1, text: "e2e5".to_string() })
+        );
+        assert_eq!(
+            OpeningBook::parse(&format!("{} => e2e4 x", start)).err(),
+            Some(BookError::InvalidWeight { line: 1, text: "x".to_string() })
+        );
+        assert!(OpeningBook::parse("").unwrap().moves(&Board::new()).is_empty());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn book_probe() {
+        use rand::SeedableRng;
+
+        let book = OpeningBook::parse(BOOK).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+
+        let board = Board::new();
+        let mut counts = HashMap::<String, u32>::new();
+        for _ in 0..760 {
+            *counts.entry(book.probe(&board, &mut rng).unwrap().to_string()).or_default() += 1;
+        }
+        // about 400, 350 and 10 of them
+        assert!((340..460).contains(&counts["e2e4"]));
+        assert!((290..410).contains(&counts["d2d4"]));
+        assert!((1..30).contains(&counts["g1f3"]));
+
+        // a move weighing 0 is never played
+        let mut board = Board::new();
+        board.apply_uci_moves(&["e2e4"]).unwrap();
+        for _ in 0..20 {
+            assert_eq!(book.probe(&board, &mut rng), Some(uci(&board, "c7c5")));
+        }
+
+        board.apply_uci_moves(&["c7c5"]).unwrap();
+        assert_eq!(book.probe(&board, &mut rng), None);
+    }
+}
@@ -1,12 +1,24 @@
 use std::{fmt::Display, str::FromStr};
 
+use super::bitboard::{self, Bitboard, Squares};
+use super::game_engine::MoveError;
+use super::zobrist::ZobristKeys;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Color {
     White,
     Black
 }
 
-#[derive(Debug, Copy, Clone)]
+impl Color {
+    pub fn opposite(&self) -> Color {
+        if *self == Color::White { Color::Black } else { Color::White }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceType {
     Pawn,
     Knight,
@@ -17,55 +29,169 @@ pub enum PieceType {
 }
 
 impl PieceType {
+    // same order as the variants and get_value
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
+    // value in centipawns as (opening, middlegame, endgame):
+    // knights lose value as the board opens up, bishops and rooks gain it,
+    // pawns grow towards promotion once the pieces are traded off
     pub fn get_value(&self, phase: GamePhase) -> u32 {
-        let value = match self {
-            PieceType::Pawn   => 1,
-            PieceType::Knight => 3,
-            PieceType::Bishop => 3,
-            PieceType::Rook   => 5,
-            PieceType::Queen  => 9,
-            PieceType::King   => u32::MAX,
+        let (opening, middlegame, endgame) = match self {
+            PieceType::Pawn   => ( 100,  100,  120),
+            PieceType::Knight => ( 320,  320,  290),
+            PieceType::Bishop => ( 330,  330,  350),
+            PieceType::Rook   => ( 480,  500,  550),
+            PieceType::Queen  => ( 900,  900,  950),
+            PieceType::King   => (u32::MAX, u32::MAX, u32::MAX),
         };
 
         match phase {
-            GamePhase::Opening    => value,
-            GamePhase::Middlegame => value,
-            GamePhase::Endgame    => value,
+            GamePhase::Opening    => opening,
+            GamePhase::Middlegame => middlegame,
+            GamePhase::Endgame    => endgame,
         }
     }
 }
 
-#[derive(Debug)]
+impl PieceType {
+    // uppercase letter as used by FEN and SAN
+    pub fn to_char(self) -> char {
+        match self {
+            PieceType::Pawn   => 'P',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Rook   => 'R',
+            PieceType::Queen  => 'Q',
+            PieceType::King   => 'K',
+        }
+    }
+
+    // accepts either case
+    pub fn from_char(ch: char) -> Option<PieceType> {
+        match ch.to_ascii_uppercase() {
+            'P' => Some(PieceType::Pawn),
+            'N' => Some(PieceType::Knight),
+            'B' => Some(PieceType::Bishop),
+            'R' => Some(PieceType::Rook),
+            'Q' => Some(PieceType::Queen),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GamePhase {
     Opening,
     Middlegame,
     Endgame
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Side {
     King,
     Queen,
 }
 
-#[derive(Debug)]
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::King  => write!(f, "kingside"),
+            Side::Queen => write!(f, "queenside"),
+        }
+    }
+}
+
+// whether a move takes something, en passant and capturing promotions included
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MoveType {
     March,
     Capture
 }
 
-#[derive(Debug)]
-pub enum MoveNature {
-    Scalar,
-    Vector
+// what a move does besides relocating the piece, so a frontend can animate
+// the castling rook or ask for the promotion piece
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveKind {
+    Normal,
+    Capture,
+    EnPassant,
+    Castle(Side),
+    Promotion(PieceType),
+    DoublePawnPush,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: Address,
+    pub to: Address,
+    pub kind: MoveKind,
+    pub move_type: MoveType,
+}
+
+impl Move {
+    // the kind and type of a move are only known against a board, so it is a Normal march
+    // here unless promoting; making the move reads nothing but the squares and the promotion
+    pub fn new(from: Address, to: Address, promotion: Option<PieceType>) -> Self {
+        let kind = promotion.map_or(MoveKind::Normal, MoveKind::Promotion);
+        Move { from, to, kind, move_type: MoveType::March }
+    }
+
+    pub fn promotion(&self) -> Option<PieceType> {
+        match self.kind {
+            MoveKind::Promotion(piece_type) => Some(piece_type),
+            _ => None,
+        }
+    }
+
+    pub fn castle_side(&self) -> Option<Side> {
+        match self.kind {
+            MoveKind::Castle(side) => Some(side),
+            _ => None,
+        }
+    }
+}
+
+// UCI notation like "e2e4" or "e7e8q"
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", super::game_engine::format_uci_move(self.from, self.to, self.promotion()))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub piece_type: PieceType,
     pub color: Color
 }
 
+impl Piece {
+    // FEN letter: uppercase for white, lowercase for black
+    pub fn to_fen_char(&self) -> char {
+        let ch = self.piece_type.to_char();
+        if self.color == Color::White { ch } else { ch.to_ascii_lowercase() }
+    }
+
+    pub fn from_fen_char(ch: char) -> Option<Piece> {
+        let piece_type = PieceType::from_char(ch)?;
+        let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+
+        Some(Piece { piece_type, color })
+    }
+}
+
 impl Display for Piece {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let val = match self.color {
@@ -100,6 +226,12 @@ pub const CELLS_COUNT: u8 = ROW_SIZE * ROW_SIZE;
 // from [a1..a8] to [h1..h8]
 pub type BoardLayer<T> = [T; CELLS_COUNT as usize];
 
+// indices into Board::castling_rights
+pub const WHITE_KING_SIDE: usize = 0;
+pub const WHITE_QUEEN_SIDE: usize = 1;
+pub const BLACK_KING_SIDE: usize = 2;
+pub const BLACK_QUEEN_SIDE: usize = 3;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Address {
     pub col: u8,
@@ -107,23 +239,42 @@ pub struct Address {
 }
 
 impl Address {
+    // panics on out-of-range col/row, use try_new for untrusted input
     pub fn new(col: u8, row: u8) -> Self {
-        assert!(col < ROW_SIZE);
-        assert!(row < ROW_SIZE);
+        Self::try_new(col, row).expect("address is out of the board")
+    }
 
-        Address { col, row }
+    pub fn try_new(col: u8, row: u8) -> Option<Self> {
+        if col < ROW_SIZE && row < ROW_SIZE {
+            Some(Address { col, row })
+        } else {
+            None
+        }
     }
 
     pub fn parse(s: &str) -> Self {
         Address::from_str(s).unwrap()
     }
 
+    // position in a BoardLayer: a1 is 0, h1 is 7, a2 is 8 and h8 is 63
+    pub fn to_index(&self) -> u8 {
+        self.row * ROW_SIZE + self.col
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        if index < CELLS_COUNT {
+            Some(Address::new(index % ROW_SIZE, index / ROW_SIZE))
+        } else {
+            None
+        }
+    }
+
     pub fn get_row_name(row: u8) -> char {
-        ('1' as u8 + row) as char
+        (b'1' + row) as char
     }
 
     pub fn get_col_name(col: u8) -> char {
-        ('a' as u8 + col) as char
+        (b'a' + col) as char
     }
 
     pub fn get_color(&self) -> Color {
@@ -140,19 +291,51 @@ impl Address {
         let new_col = (self.col as i8) + col_offset;
         let new_row = (self.row as i8) + row_offset;
 
-        if new_row >= 0
-        && new_row < (ROW_SIZE as i8)
-        && new_col >= 0
-        && new_col < (ROW_SIZE as i8) {
-            Some(Address::new(new_col as u8, new_row as u8))
-        } else {
-            None
+        if new_row < 0 || new_col < 0 {
+            return None
         }
+
+        Address::try_new(new_col as u8, new_row as u8)
+    }
+
+    // squares shifted by the offset again and again until the board edge, the address itself excluded
+    pub fn ray(&self, offset: (i8, i8)) -> impl Iterator<Item = Address> {
+        std::iter::successors(self.get_shifted(offset), move |addr| addr.get_shifted(offset))
+    }
+
+    // up to 8 adjacent squares
+    pub fn neighbors(&self) -> impl Iterator<Item = Address> {
+        let address = *self;
+        (-1..=1)
+            .flat_map(|col_offset| (-1..=1).map(move |row_offset| (col_offset, row_offset)))
+            .filter(|offset| *offset != (0, 0))
+            .filter_map(move |offset| address.get_shifted(offset))
     }
 }
 
+// what is wrong with the text, so a UI can point at it:
+// "e4 " is WrongLength(3), "j5" is BadFile('j') and "c0" is BadRank('0')
 #[derive(Debug, PartialEq, Eq)]
-pub struct ParseAddressError;
+pub enum ParseAddressError {
+    WrongLength(usize),
+    BadFile(char),
+    BadRank(char),
+    // a square index past h8
+    OutOfBoard(usize),
+}
+
+impl Display for ParseAddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseAddressError::WrongLength(length) => write!(f, "a square takes 2 characters, not {}", length),
+            ParseAddressError::BadFile(ch)         => write!(f, "'{}' is not a file from a to h", ch),
+            ParseAddressError::BadRank(ch)         => write!(f, "'{}' is not a rank from 1 to 8", ch),
+            ParseAddressError::OutOfBoard(index)   => write!(f, "square index {} is off the board", index),
+        }
+    }
+}
+
+impl std::error::Error for ParseAddressError {}
 
 impl FromStr for Address {
     type Err = ParseAddressError;
@@ -163,51 +346,239 @@ impl FromStr for Address {
         let chars = s.chars().collect::<Vec<_>>();
 
         if chars.len() != 2 {
-            return Err(ParseAddressError)
+            return Err(ParseAddressError::WrongLength(chars.len()))
         }
 
         let col = chars[0].to_ascii_lowercase();
         let row = chars[1].to_ascii_lowercase();
 
         if let c @ 'a'..='h' = col {
-            res.col = (c as u8) - ('a' as u8)
+            res.col = (c as u8) - b'a'
         } else {
-            return Err(ParseAddressError)
+            return Err(ParseAddressError::BadFile(chars[0]))
         }
 
         if let r @ '1'..='8' = row {
-            res.row = (r as u8) - ('1' as u8)
+            res.row = (r as u8) - b'1'
         } else {
-            return Err(ParseAddressError)
+            return Err(ParseAddressError::BadRank(chars[1]))
         }
-        
+
         Ok(res)
     }
 }
 
+// human-readable algebraic form like "e4" rather than {col, row}
+#[cfg(feature = "serde")]
+impl serde::Serialize for Address {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        Address::from_str(&s).map_err(|_| serde::de::Error::custom(format!("invalid address \"{}\"", s)))
+    }
+}
+
 impl Display for Address {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}{}", Self::get_col_name(self.col), Self::get_row_name(self.row))
     }
 }
 
+// serde derives only cover arrays up to 32 items, so the 64 cells go as a sequence
+#[cfg(feature = "serde")]
+mod serde_layer {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::{Piece, Squares};
+
+    pub fn serialize<S: Serializer>(squares: &Squares, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(squares.cells().iter())
+    }
+
+    // bitboards are not serialized, they are rebuilt from the cells
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Squares, D::Error> {
+        let cells = Vec::<Option<Piece>>::deserialize(deserializer)?;
+        let len = cells.len();
+        let cells = cells.try_into().map_err(|_| D::Error::invalid_length(len, &"64 board cells"))?;
+        Ok(Squares::from_layer(cells))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    WrongRankSize{rank: u8, squares: usize},
+    IllegalCharacter(char),
+    InvalidActiveColor(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidCounter(String),
+    // the following come from game_engine::validate_fen only, from_fen itself accepts
+    // any arrangement so study positions without kings still load
+    KingCount{color: Color, count: u32},
+    PawnOnBackRank(Address),
+    EnPassantMismatch(Address),
+}
+
+// everything needed to take a move back
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveRecord {
+    pub from: Address,
+    pub to: Address,
+    pub piece: Piece,
+    pub captured: Option<Piece>,
+    pub promotion: Option<PieceType>,
+    pub castling_rights: [bool; 4],
+    pub en_passant_target: Option<Address>,
+    pub halfmove_clock: u32,
+    // home square of the rook when the move is castling
+    pub castling_rook: Option<Address>,
+    // the position before the move, checked when the move is taken back
+    #[cfg(debug_assertions)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) before: Option<PositionSnapshot>,
+}
+
+// debug builds only: all a move and its unmaking may touch, so a board that
+// is not restored exactly is caught right in unmake_move. the zobrist hash
+// is computed from these fields, so it is covered as well
+#[cfg(debug_assertions)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct PositionSnapshot {
+    pieces: [Bitboard; 12],
+    whose_turn: Color,
+    castling_rights: [bool; 4],
+    castling_rook_cols: [u8; 2],
+    en_passant_target: Option<Address>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    graveyards: [usize; 2],
+}
+
+#[cfg(debug_assertions)]
+impl PositionSnapshot {
+    pub(crate) fn of(board: &Board) -> Self {
+        let mut pieces = [0; 12];
+        let all = [Color::White, Color::Black].into_iter()
+            .flat_map(|color| PieceType::ALL.into_iter().map(move |piece_type| Piece { piece_type, color }));
+        for (bitboard, piece) in pieces.iter_mut().zip(all) {
+            *bitboard = board.bitboard(piece);
+        }
+
+        PositionSnapshot {
+            pieces,
+            whose_turn: board.whose_turn,
+            castling_rights: board.castling_rights,
+            castling_rook_cols: board.castling_rook_cols,
+            en_passant_target: board.en_passant_target,
+            halfmove_clock: board.halfmove_clock,
+            fullmove_number: board.fullmove_number,
+            graveyards: [board.white_graveyard.len(), board.black_graveyard.len()],
+        }
+    }
+}
+
+impl MoveRecord {
+    pub fn is_castling(&self) -> bool {
+        self.castling_rook.is_some()
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.piece.piece_type == PieceType::Pawn
+            && self.from.col != self.to.col
+            && self.en_passant_target == Some(self.to)
+    }
+
+    // the move as it was played, with the kind it had on the board before it
+    pub fn to_move(&self) -> Move {
+        let kind = if let Some(rook) = self.castling_rook {
+            MoveKind::Castle(if rook.col > self.from.col { Side::King } else { Side::Queen })
+        } else if let Some(promotion) = self.promotion {
+            MoveKind::Promotion(promotion)
+        } else if self.is_en_passant() {
+            MoveKind::EnPassant
+        } else if self.piece.piece_type == PieceType::Pawn && self.from.row.abs_diff(self.to.row) == 2 {
+            MoveKind::DoublePawnPush
+        } else if self.captured.is_some() {
+            MoveKind::Capture
+        } else {
+            MoveKind::Normal
+        };
+
+        let move_type = if self.captured.is_some() { MoveType::Capture } else { MoveType::March };
+
+        Move { from: self.from, to: self.to, kind, move_type }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Board {
-    pub pieces: BoardLayer<Option<Piece>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_layer"))]
+    pieces: Squares,
     pub whose_turn: Color,
     pub flip_board: bool,
+    // show the board from the side to move, overrides flip_board while set
+    pub auto_flip: bool,
+    pub castling_rights: [bool; 4],
+    // home columns of the king side and queen side rooks, h and a unless chess960
+    pub castling_rook_cols: [u8; 2],
+    // chess960 castling is written as the king moving onto its own rook
+    pub chess960: bool,
+    pub en_passant_target: Option<Address>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
 
     pub white_graveyard: Vec<Piece>,
     pub black_graveyard: Vec<Piece>,
+
+    pub history: Vec<MoveRecord>,
+    pub redo_history: Vec<MoveRecord>,
 }
 
+// boards are equal when their positions are: pieces, side to move, castling,
+// en passant and the clocks. graveyards and history are how the position came
+// about and the flip settings are display only, so none of them are compared
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.pieces == other.pieces
+            && self.whose_turn == other.whose_turn
+            && self.castling_rights == other.castling_rights
+            && self.castling_rook_cols == other.castling_rook_cols
+            && self.chess960 == other.chess960
+            && self.en_passant_target == other.en_passant_target
+            && self.halfmove_clock == other.halfmove_clock
+            && self.fullmove_number == other.fullmove_number
+    }
+}
+
+impl Eq for Board {}
+
 impl Default for Board {
     fn default() -> Self {
         Self {
-            pieces: [None; CELLS_COUNT as usize],
+            pieces: Squares::default(),
             whose_turn: Color::White,
             flip_board: false,
+            auto_flip: false,
+            castling_rights: [false; 4],
+            castling_rook_cols: [7, 0],
+            chess960: false,
+            en_passant_target: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
             white_graveyard: Vec::new(),
-            black_graveyard: Vec::new()
+            black_graveyard: Vec::new(),
+            history: Vec::new(),
+            redo_history: Vec::new(),
         }
     }
 }
@@ -228,7 +599,7 @@ impl Board {
 
         use PieceType::*;
         Board {
-            pieces: [
+            pieces: Squares::from_layer([
                 w(Rook), w(Knight), w(Bishop), w(Queen), w(King), w(Bishop), w(Knight), w(Rook),
                 w(Pawn), w(Pawn),   w(Pawn),   w(Pawn),  w(Pawn), w(Pawn),   w(Pawn),   w(Pawn),
                 None,    None,      None,      None,     None,    None,      None,      None,
@@ -237,63 +608,711 @@ impl Board {
                 None,    None,      None,      None,     None,    None,      None,      None,
                 b(Pawn), b(Pawn),   b(Pawn),   b(Pawn),  b(Pawn), b(Pawn),   b(Pawn),   b(Pawn),
                 b(Rook), b(Knight), b(Bishop), b(Queen), b(King), b(Bishop), b(Knight), b(Rook)
-            ],
+            ]),
+            castling_rights: [true; 4],
             ..Default::default()
         }
     }
 
-    pub fn new_empty() -> Self {
-        Board {
-            pieces: [None; CELLS_COUNT as usize],
+    // Fischer random start by the standard numbering, None unless position_number < 960;
+    // 518 is the usual chess start
+    pub fn new_chess960(position_number: u16) -> Option<Self> {
+        static POSITIONS_COUNT: u16 = 960;
+        // knight placements among the five files left after the bishops and the queen
+        static KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+            (0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+        ];
+
+        if position_number >= POSITIONS_COUNT {
+            return None;
+        }
+
+        use PieceType::*;
+        let mut rank = [None::<PieceType>; ROW_SIZE as usize];
+        let mut n = position_number as usize;
+
+        // light squared bishop on b, d, f or h, dark squared one on a, c, e or g
+        rank[(n % 4) * 2 + 1] = Some(Bishop);
+        n /= 4;
+        rank[(n % 4) * 2] = Some(Bishop);
+        n /= 4;
+
+        let place = |rank: &mut [Option<PieceType>; ROW_SIZE as usize], nth_free: usize, piece_type: PieceType| {
+            let col = (0..ROW_SIZE as usize).filter(|c| rank[*c].is_none()).nth(nth_free).unwrap();
+            rank[col] = Some(piece_type);
+        };
+
+        place(&mut rank, n % 6, Queen);
+        n /= 6;
+
+        // the second knight is placed after the first one took its file
+        let (first, second) = KNIGHT_PLACEMENTS[n];
+        place(&mut rank, first, Knight);
+        place(&mut rank, second - 1, Knight);
+
+        // the king always stands between the rooks
+        place(&mut rank, 0, Rook);
+        place(&mut rank, 0, King);
+        place(&mut rank, 0, Rook);
+
+        let mut board = Board {
+            castling_rights: [true; 4],
+            chess960: true,
             ..Default::default()
+        };
+
+        let rook_cols = (0..ROW_SIZE).filter(|c| rank[*c as usize] == Some(Rook)).collect::<Vec<_>>();
+        board.castling_rook_cols = [rook_cols[1], rook_cols[0]];
+
+        for (col, piece_type) in rank.iter().enumerate() {
+            let piece_type = piece_type.unwrap();
+            let col = col as u8;
+            board.set_cell(Address::new(col, 0), Some(Piece { piece_type, color: Color::White }));
+            board.set_cell(Address::new(col, 1), Some(Piece { piece_type: Pawn, color: Color::White }));
+            board.set_cell(Address::new(col, ROW_SIZE - 2), Some(Piece { piece_type: Pawn, color: Color::Black }));
+            board.set_cell(Address::new(col, ROW_SIZE - 1), Some(Piece { piece_type, color: Color::Black }));
+        }
+
+        Some(board)
+    }
+
+    pub fn new_empty() -> Self {
+        Board::default()
+    }
+
+    // accepts both full six-field FEN and the four-field form without move counters
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields = fen.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()))
+        }
+
+        let mut board = Board::new_empty();
+
+        let ranks = fields[0].split('/').collect::<Vec<_>>();
+        if ranks.len() != ROW_SIZE as usize {
+            return Err(FenError::WrongRankCount(ranks.len()))
+        }
+
+        // FEN lists ranks from the 8th down to the 1st
+        for (i, rank) in ranks.iter().enumerate() {
+            let row = ROW_SIZE - 1 - i as u8;
+            let mut col = 0usize;
+
+            for ch in rank.chars() {
+                if let Some(skip) = ch.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                    col += skip as usize;
+                    continue;
+                }
+
+                let piece = Piece::from_fen_char(ch).ok_or(FenError::IllegalCharacter(ch))?;
+                if col < ROW_SIZE as usize {
+                    board.set_cell(Address::new(col as u8, row), Some(piece));
+                }
+                col += 1;
+            }
+
+            if col != ROW_SIZE as usize {
+                return Err(FenError::WrongRankSize { rank: row + 1, squares: col })
+            }
+        }
+
+        board.whose_turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(FenError::InvalidActiveColor(other.to_string())),
+        };
+
+        // KQkq, or for chess960 the Shredder-FEN files of the castling rooks like HAha:
+        // the king's own file tells on which side a rook stands
+        if fields[2] != "-" {
+            let invalid_castling = || FenError::InvalidCastling(fields[2].to_string());
+            let mut rook_cols = [None; 2];
+
+            for ch in fields[2].chars() {
+                let right = match ch {
+                    'K' => WHITE_KING_SIDE,
+                    'Q' => WHITE_QUEEN_SIDE,
+                    'k' => BLACK_KING_SIDE,
+                    'q' => BLACK_QUEEN_SIDE,
+                    'A'..='H' | 'a'..='h' => {
+                        let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+                        let col = ch.to_ascii_lowercase() as u8 - b'a';
+                        let home_row = if color == Color::White { 0 } else { ROW_SIZE - 1 };
+                        let king = board.find_king(color)
+                            .filter(|king| king.row == home_row)
+                            .ok_or_else(invalid_castling)?;
+
+                        let side = if col > king.col { 0 } else { 1 };
+                        if rook_cols[side].is_some_and(|other| other != col) {
+                            return Err(invalid_castling());
+                        }
+                        rook_cols[side] = Some(col);
+                        board.chess960 = true;
+
+                        if color == Color::White { side } else { BLACK_KING_SIDE + side }
+                    },
+                    _ => return Err(invalid_castling()),
+                };
+                board.castling_rights[right] = true;
+            }
+
+            for (rook_col, col) in board.castling_rook_cols.iter_mut().zip(rook_cols) {
+                *rook_col = col.unwrap_or(*rook_col);
+            }
+        }
+
+        if fields[3] != "-" {
+            let target = Address::from_str(fields[3])
+                .map_err(|_| FenError::InvalidEnPassant(fields[3].to_string()))?;
+            board.en_passant_target = Some(target);
+        }
+
+        if fields.len() == 6 {
+            let parse_counter = |s: &str| -> Result<u32, FenError> {
+                s.parse::<u32>().map_err(|_| FenError::InvalidCounter(s.to_string()))
+            };
+
+            board.halfmove_clock = parse_counter(fields[4])?;
+            board.fullmove_number = parse_counter(fields[5])?;
+        }
+
+        Ok(board)
+    }
+
+    // a diagram like Display or render_ascii print, rank 8 on top: FEN letters or
+    // unicode glyphs for pieces and '.' for empty squares; rank and file labels,
+    // borders and graveyard lines are skipped. White moves first and nobody may castle.
+    // once any line carries a rank label, unlabeled lines are not taken as ranks
+    pub fn from_ascii(diagram: &str) -> Result<Self, FenError> {
+        static VARIATION_SELECTOR: char = '\u{fe0e}'; // follows the black pawn glyph
+
+        let from_glyph = |ch: char| -> Option<Piece> {
+            [Color::White, Color::Black].into_iter()
+                .flat_map(|color| PieceType::ALL.into_iter().map(move |piece_type| Piece { piece_type, color }))
+                .find(|piece| piece.to_string().starts_with(ch))
+        };
+
+        let mut ranks = Vec::<(bool, Vec<Option<Piece>>)>::new();
+
+        for line in diagram.lines() {
+            let compact = line.chars().filter(|ch| !ch.is_whitespace()).collect::<String>();
+            if compact.contains(':') || compact == "abcdefgh" || compact == "hgfedcba" {
+                continue;
+            }
+
+            let mut cells = Vec::new();
+            for ch in compact.chars() {
+                match ch {
+                    '.' => cells.push(None),
+                    '|' | '+' | '-' => {},
+                    _ if ch == VARIATION_SELECTOR || ch.is_ascii_digit() => {},
+                    _ => {
+                        let piece = Piece::from_fen_char(ch)
+                            .or_else(|| from_glyph(ch))
+                            .ok_or(FenError::IllegalCharacter(ch))?;
+                        cells.push(Some(piece));
+                    },
+                }
+            }
+
+            if !cells.is_empty() {
+                ranks.push((compact.starts_with(|ch: char| ch.is_ascii_digit()), cells));
+            }
+        }
+
+        if ranks.iter().any(|(is_labeled, _)| *is_labeled) {
+            ranks.retain(|(is_labeled, _)| *is_labeled);
+        }
+
+        if ranks.len() != ROW_SIZE as usize {
+            return Err(FenError::WrongRankCount(ranks.len()))
         }
+
+        let mut board = Board::new_empty();
+        for (i, (_, rank)) in ranks.iter().enumerate() {
+            let row = ROW_SIZE - 1 - i as u8;
+            if rank.len() != ROW_SIZE as usize {
+                return Err(FenError::WrongRankSize { rank: row + 1, squares: rank.len() })
+            }
+
+            for (col, cell) in rank.iter().enumerate() {
+                board.set_cell(Address::new(col as u8, row), *cell);
+            }
+        }
+
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut res = String::new();
+
+        for r in (0..ROW_SIZE).rev() {
+            let mut empty = 0;
+
+            for c in 0..ROW_SIZE {
+                if let Some(piece) = self.get_cell(Address::new(c, r)) {
+                    if empty > 0 {
+                        res += &empty.to_string();
+                        empty = 0;
+                    }
+                    res.push(piece.to_fen_char());
+                } else {
+                    empty += 1;
+                }
+            }
+
+            if empty > 0 {
+                res += &empty.to_string();
+            }
+            if r > 0 {
+                res.push('/');
+            }
+        }
+
+        res.push(' ');
+        res.push(if self.whose_turn == Color::White { 'w' } else { 'b' });
+        res.push(' ');
+
+        // chess960 rooks may stand anywhere, so their files are written instead
+        let castling = ['K', 'Q', 'k', 'q'].iter().enumerate()
+            .filter(|(right, _)| self.castling_rights[*right])
+            .map(|(right, ch)| {
+                if !self.chess960 {
+                    return *ch;
+                }
+                let file = (b'a' + self.castling_rook_cols[right % 2]) as char;
+                if right < BLACK_KING_SIDE { file.to_ascii_uppercase() } else { file }
+            })
+            .collect::<String>();
+        res += if castling.is_empty() { "-" } else { &castling };
+
+        res.push(' ');
+        match self.en_passant_target {
+            Some(target) => res += &target.to_string(),
+            None => res.push('-'),
+        }
+
+        res += &format!(" {} {}", self.halfmove_clock, self.fullmove_number);
+        res
     }
 
-    fn get_index(address: Address) -> u8 {
-        address.row * ROW_SIZE + address.col
+    // occupied squares only, from a1 to h8
+    pub fn iter_pieces(&self) -> impl Iterator<Item = (Address, Piece)> + '_ {
+        bitboard::addresses(self.occupied())
+            .filter_map(|address| self.get_cell(address).map(|piece| (address, piece)))
+    }
+
+    // None for malformed positions without a king of that color
+    pub fn find_king(&self, color: Color) -> Option<Address> {
+        bitboard::addresses(self.bitboard(Piece { piece_type: PieceType::King, color })).next()
+    }
+
+    // centipawns of the live pieces of that color, kept up to date on every cell change
+    pub fn material_count(&self, color: Color) -> u32 {
+        let res = self.pieces.material(color);
+        debug_assert_eq!(
+            res,
+            self.iter_pieces()
+                .filter(|(_, piece)| piece.color == color)
+                .map(|(_, piece)| Self::get_material(piece))
+                .sum::<u32>(),
+            "incremental material drifted from the pieces on the board"
+        );
+        res
+    }
+
+    // white minus black in centipawns
+    pub fn material_balance(&self) -> i32 {
+        self.material_count(Color::White) as i32 - self.material_count(Color::Black) as i32
+    }
+
+    // the king is priceless and would overflow the sum, so it counts as nothing
+    pub(crate) fn get_material(piece: Piece) -> u32 {
+        match piece.piece_type {
+            PieceType::King => 0,
+            piece_type      => piece_type.get_value(GamePhase::Middlegame),
+        }
     }
 
     pub fn get_cell(&self, address: Address) -> &Option<Piece> {
-        &self.pieces[Self::get_index(address) as usize]
+        self.pieces.get(address.to_index() as usize)
+    }
+
+    pub fn has_piece(&self, address: Address) -> bool {
+        self.get_cell(address).is_some()
     }
 
-    pub fn get_cell_mut(&mut self, address: Address) -> &mut Option<Piece> {
-        &mut self.pieces[Self::get_index(address) as usize]
+    // pieces of both colors, kings included
+    pub fn piece_count(&self) -> usize {
+        self.occupied().count_ones() as usize
     }
 
+    // squares of that color's pieces from a1 to h8
+    pub fn pieces_of(&self, color: Color) -> Vec<Address> {
+        bitboard::addresses(self.occupancy(color)).collect()
+    }
+
+    // cells are only written through here so that the bitboards stay in sync
+    pub fn set_cell(&mut self, address: Address, cell: Option<Piece>) {
+        self.pieces.set(address.to_index() as usize, cell);
+    }
+
+    // position setup for editors, puzzles and tests: the piece is written as is,
+    // nothing goes to a graveyard and castling rights, en passant and clocks stay untouched
+    pub fn set_piece(&mut self, address: Address, piece: Option<Piece>) {
+        self.set_cell(address, piece);
+    }
+
+    // setup counterpart of set_piece, returns what stood there
+    pub fn remove_piece(&mut self, address: Address) -> Option<Piece> {
+        let piece = *self.get_cell(address);
+        self.set_cell(address, None);
+        piece
+    }
+
+    // squares occupied by that exact piece
+    pub fn bitboard(&self, piece: Piece) -> Bitboard {
+        self.pieces.pieces(piece)
+    }
+
+    pub fn occupancy(&self, color: Color) -> Bitboard {
+        self.pieces.color(color)
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.pieces.occupied()
+    }
+
+    // manual flips start from what is shown right now and turn auto-flip off
     pub fn flip_board(&mut self) {
-        self.flip_board = !self.flip_board;
+        self.flip_board = !self.is_flipped();
+        self.auto_flip = false;
     }
 
     pub fn flip_board_for(&mut self, color: Color) {
         self.flip_board = color == Color::Black;
+        self.auto_flip = false;
+    }
+
+    // flip_board is kept aside, so switching auto-flip off brings the manual orientation back
+    pub fn set_auto_flip(&mut self, auto_flip: bool) {
+        self.auto_flip = auto_flip;
+    }
+
+    // whether Display shows the board from black's side
+    pub fn is_flipped(&self) -> bool {
+        if self.auto_flip {
+            self.whose_turn == Color::Black
+        } else {
+            self.flip_board
+        }
+    }
+
+    // setup counterpart of flip_player: changing the side to move drops the en passant target,
+    // since it was only ever capturable by the side that was to move. the zobrist hash is
+    // computed from whose_turn, so it follows on its own. The history goes as well: no move
+    // leads to the new position, so none can be taken back from it
+    pub fn set_turn(&mut self, color: Color) {
+        if self.whose_turn != color {
+            self.whose_turn = color;
+            self.en_passant_target = None;
+            self.history.clear();
+            self.redo_history.clear();
+        }
     }
 
     pub fn flip_player(&mut self) {
-        self.whose_turn = if self.whose_turn == Color::White { Color::Black } else {Color::White};
+        self.whose_turn = self.whose_turn.opposite();
+    }
+
+    // the same position with the colors swapped: ranks turned upside down, every piece,
+    // the side to move, castling rights and captured pieces handed to the other color.
+    // the history is not carried over
+    pub fn mirror_vertical(&self) -> Board {
+        let mirror = |address: Address| Address::new(address.col, ROW_SIZE - 1 - address.row);
+        let swap_color = |piece: &Piece| Piece { piece_type: piece.piece_type, color: piece.color.opposite() };
+
+        let mut res = Board {
+            whose_turn: self.whose_turn.opposite(),
+            castling_rights: [
+                self.castling_rights[BLACK_KING_SIDE],
+                self.castling_rights[BLACK_QUEEN_SIDE],
+                self.castling_rights[WHITE_KING_SIDE],
+                self.castling_rights[WHITE_QUEEN_SIDE],
+            ],
+            castling_rook_cols: self.castling_rook_cols,
+            chess960: self.chess960,
+            en_passant_target: self.en_passant_target.map(mirror),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            white_graveyard: self.black_graveyard.iter().map(swap_color).collect(),
+            black_graveyard: self.white_graveyard.iter().map(swap_color).collect(),
+            ..Default::default()
+        };
+
+        for (address, piece) in self.iter_pieces() {
+            res.set_cell(mirror(address), Some(swap_color(&piece)));
+        }
+
+        res
     }
 
     pub fn kill_piece(&mut self, address: Address) {
-        let index = Self::get_index(address) as usize;
-        if let Some(piece) = self.pieces[index] {
+        let index = address.to_index() as usize;
+        if let Some(piece) = *self.pieces.get(index) {
             if piece.color == Color::White {
                 self.white_graveyard.push(piece)
             } else {
                 self.black_graveyard.push(piece)
             }
 
-            self.pieces[index] = None
+            self.pieces.set(index, None)
         }
     }
 
+    // any move of a king, or from or onto a rook home square, loses the related rights
+    fn update_castling_rights(&mut self, address: Address) {
+        let rights = [
+            (WHITE_KING_SIDE, Color::White),
+            (WHITE_QUEEN_SIDE, Color::White),
+            (BLACK_KING_SIDE, Color::Black),
+            (BLACK_QUEEN_SIDE, Color::Black),
+        ];
+
+        for (right, color) in rights {
+            let home_row = if color == Color::White { 0 } else { ROW_SIZE - 1 };
+            let rook_home = Address::new(self.castling_rook_cols[right % 2], home_row);
+            let is_king = matches!(
+                self.get_cell(address),
+                Some(piece) if piece.piece_type == PieceType::King && piece.color == color
+            );
+
+            if address == rook_home || is_king {
+                self.castling_rights[right] = false;
+            }
+        }
+    }
+
+    // where the king and the rook end up: g and f files on the king side, c and d on the queen side
+    pub(crate) fn get_castled_squares(king_from: Address, rook_from: Address) -> (Address, Address) {
+        let (king_col, rook_col) = if rook_from.col > king_from.col { (6, 5) } else { (2, 3) };
+        (Address::new(king_col, king_from.row), Address::new(rook_col, king_from.row))
+    }
+
+    // both pieces leave the board before landing, as in chess960 they may swap squares
+    pub(crate) fn castle(&mut self, king_from: Address, rook_from: Address) {
+        let (king_to, rook_to) = Self::get_castled_squares(king_from, rook_from);
+        let king = *self.get_cell(king_from);
+        let rook = *self.get_cell(rook_from);
+
+        self.update_castling_rights(king_from);
+        self.en_passant_target = None;
+
+        self.set_cell(king_from, None);
+        self.set_cell(rook_from, None);
+        self.set_cell(king_to, king);
+        self.set_cell(rook_to, rook);
+    }
+
     pub fn move_piece(&mut self, from: Address, to: Address) {
+        self.update_castling_rights(from);
+        self.update_castling_rights(to);
+
+        // the skipped square of a pawn's long march stays capturable for one move
+        self.en_passant_target = match self.get_cell(from) {
+            Some(piece) if piece.piece_type == PieceType::Pawn && from.row.abs_diff(to.row) == 2 => {
+                Some(Address::new(from.col, (from.row + to.row) / 2))
+            },
+            _ => None,
+        };
+
         self.kill_piece(to);
         
-        let index_from = Self::get_index(from) as usize;
-        let index_to = Self::get_index(to) as usize;
+        let index_from = from.to_index() as usize;
+        let index_to = to.to_index() as usize;
+
+        self.pieces.set(index_to, *self.pieces.get(index_from));
+        self.pieces.set(index_from, None)
+    }
+
+    // half-moves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn zobrist_hash(&self) -> u64 {
+        let keys = ZobristKeys::get();
+        let mut hash = keys.side(self.whose_turn);
+
+        for (addr, piece) in self.iter_pieces() {
+            hash ^= keys.piece(addr, piece);
+        }
+
+        for (right, allowed) in self.castling_rights.iter().enumerate() {
+            if *allowed {
+                hash ^= keys.castling(right);
+            }
+        }
+
+        if let Some(target) = self.en_passant_target {
+            if self.can_capture_en_passant(target) {
+                hash ^= keys.en_passant(target.col);
+            }
+        }
+
+        hash
+    }
+
+    // whether a pawn of the side to move stands next to the en passant victim
+    pub(crate) fn can_capture_en_passant(&self, target: Address) -> bool {
+        let pawn = Some(Piece { piece_type: PieceType::Pawn, color: self.whose_turn });
+        let row_offset = if self.whose_turn == Color::White { -1 } else { 1 };
+
+        [-1, 1].iter().any(|col_offset| {
+            match target.get_shifted((*col_offset, row_offset)) {
+                Some(addr) => *self.get_cell(addr) == pawn,
+                None => false,
+            }
+        })
+    }
+
+    // bordered ASCII board seen from `perspective`, with each side's captures
+    // listed next to that side; letters keep every cell one column wide
+    pub fn render_ascii(&self, perspective: Color) -> String {
+        let is_flipped = perspective == Color::Black;
+        let row_order = |i: u8| -> u8 { if is_flipped { i } else { ROW_SIZE - 1 - i } };
+        let col_order = |i: u8| -> u8 { if is_flipped { ROW_SIZE - 1 - i } else { i } };
+
+        // white captured what lies in the black graveyard and vice versa
+        let captured = |color: Color| -> String {
+            let (label, graveyard) = if color == Color::White {
+                ("White captured:", &self.black_graveyard)
+            } else {
+                ("Black captured:", &self.white_graveyard)
+            };
+
+            let mut line = label.to_string();
+            for piece in graveyard {
+                line.push(' ');
+                line.push(piece.to_fen_char());
+            }
+            line
+        };
+
+        let mut files = String::from("   ");
+        for c in 0..ROW_SIZE {
+            files.push(' ');
+            files.push(Address::get_col_name(col_order(c)));
+        }
+        let border = format!("  +{}+", "-".repeat(ROW_SIZE as usize * 2 + 1));
 
-        self.pieces[index_to] = self.pieces[index_from];
-        self.pieces[index_from] = None
+        let mut lines = vec![captured(perspective.opposite()), files.clone(), border.clone()];
+
+        for r in 0..ROW_SIZE {
+            let row = row_order(r);
+            let mut line = format!("{} |", Address::get_row_name(row));
+
+            for c in 0..ROW_SIZE {
+                line.push(' ');
+                line.push(match self.get_cell(Address::new(col_order(c), row)) {
+                    Some(piece) => piece.to_fen_char(),
+                    None => '.',
+                });
+            }
+
+            line += &format!(" | {}", Address::get_row_name(row));
+            lines.push(line);
+        }
+
+        lines.push(border);
+        lines.push(files);
+        lines.push(captured(perspective));
+
+        lines.join("\n")
+    }
+
+    // takes back the last played move, returns false if there is nothing to undo
+    pub fn undo_move(&mut self) -> bool {
+        match self.unmake_move() {
+            Some(record) => {
+                self.redo_history.push(record);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // restores the position before the last move without touching the redo history
+    pub(crate) fn unmake_move(&mut self) -> Option<MoveRecord> {
+        let record = self.history.pop()?;
+
+        if let Some(rook_from) = record.castling_rook {
+            let (king_to, rook_to) = Self::get_castled_squares(record.from, rook_from);
+            let rook = *self.get_cell(rook_to);
+            self.set_cell(king_to, None);
+            self.set_cell(rook_to, None);
+            self.set_cell(rook_from, rook);
+        }
+
+        self.set_cell(record.from, Some(record.piece));
+        if record.castling_rook != Some(record.to) {
+            self.set_cell(record.to, None);
+        }
+
+        if let Some(captured) = record.captured {
+            let captured_address = if record.is_en_passant() {
+                Address::new(record.to.col, record.from.row)
+            } else {
+                record.to
+            };
+            self.set_cell(captured_address, Some(captured));
+
+            if captured.color == Color::White {
+                self.white_graveyard.pop();
+            } else {
+                self.black_graveyard.pop();
+            }
+        }
+
+        self.castling_rights = record.castling_rights;
+        self.en_passant_target = record.en_passant_target;
+        self.halfmove_clock = record.halfmove_clock;
+        if record.piece.color == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        // making a move always passes the turn, even a trial move of the side not to move
+        self.flip_player();
+
+        #[cfg(debug_assertions)]
+        if let Some(before) = record.before {
+            debug_assert_eq!(PositionSnapshot::of(self), before, "unmake_move did not restore the position");
+        }
+
+        Some(record)
+    }
+
+    // replays the last undone move, returns false if there is nothing to redo
+    pub fn redo_move(&mut self) -> bool {
+        let record = match self.redo_history.pop() {
+            Some(record) => record,
+            None => return false,
+        };
+
+        let promotion = record.promotion.unwrap_or(PieceType::Queen);
+        super::game_engine::make_move_unchecked_promoting(self, record.from, record.to, promotion);
+        true
+    }
+
+    // plays long algebraic moves like "e2e4" or "e7e8q" one by one, on an error the moves
+    // before the bad one stay played
+    pub fn apply_uci_moves(&mut self, moves: &[&str]) -> Result<(), MoveError> {
+        for mv in moves {
+            let (from, to, promotion) = super::game_engine::parse_uci_move(mv)?;
+            super::game_engine::make_move(self, Move::new(from, to, promotion))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -309,8 +1328,10 @@ impl Display for Board {
         }
         res += "\n";
 
+        let is_flipped = self.is_flipped();
+
         for r in 0..ROW_SIZE {
-            let r = if !self.flip_board {
+            let r = if !is_flipped {
                 ROW_SIZE - r - 1
             } else {
                 r
@@ -320,7 +1341,7 @@ impl Display for Board {
             res += " ";
 
             for c in 0..ROW_SIZE {
-                let c = if self.flip_board {
+                let c = if is_flipped {
                     ROW_SIZE - c - 1
                 } else {
                     c
@@ -342,7 +1363,7 @@ impl Display for Board {
         res += "  ";
 
         for c in 0..ROW_SIZE {
-            let c = if self.flip_board {
+            let c = if is_flipped {
                 ROW_SIZE - c - 1
             } else {
                 c
@@ -368,33 +1389,90 @@ mod test {
     use std::{cell::Cell};
 
     use super::*;
+    use super::super::game_engine;
+
+    #[test]
+    fn piece_type_all() {
+        assert_eq!(PieceType::ALL.len(), 6);
+
+        for (i, piece_type) in PieceType::ALL.iter().enumerate() {
+            assert_eq!(*piece_type as usize, i);
+            assert_eq!(PieceType::ALL.iter().filter(|t| *t == piece_type).count(), 1);
+        }
+    }
+
+    #[test]
+    fn piece_values_by_phase() {
+        use PieceType::*;
+        use GamePhase::*;
+
+        assert_eq!(Pawn.get_value(Middlegame), 100);
+        assert!(Pawn.get_value(Endgame) > Pawn.get_value(Middlegame));
+        assert!(Knight.get_value(Middlegame) > Knight.get_value(Endgame));
+        assert!(Bishop.get_value(Endgame) > Bishop.get_value(Middlegame));
+        assert!(Rook.get_value(Endgame) > Rook.get_value(Opening));
+
+        for phase in [Opening, Middlegame, Endgame] {
+            assert!(Pawn.get_value(phase) < Knight.get_value(phase));
+            assert!(Knight.get_value(phase) < Rook.get_value(phase));
+            assert!(Bishop.get_value(phase) < Rook.get_value(phase));
+            assert!(Rook.get_value(phase) < Queen.get_value(phase));
+            assert!(Queen.get_value(phase) < King.get_value(phase));
+        }
+    }
+
+    #[test]
+    fn piece_chars() {
+        use PieceType::*;
+        for piece_type in PieceType::ALL {
+            let ch = piece_type.to_char();
+            assert!(ch.is_ascii_uppercase());
+            assert_eq!(PieceType::from_char(ch), Some(piece_type));
+            assert_eq!(PieceType::from_char(ch.to_ascii_lowercase()), Some(piece_type));
+
+            for color in [Color::White, Color::Black] {
+                let piece = Piece { piece_type, color };
+                assert_eq!(Piece::from_fen_char(piece.to_fen_char()), Some(piece));
+            }
+        }
+
+        assert_eq!(Piece { piece_type: Knight, color: Color::White }.to_fen_char(), 'N');
+        assert_eq!(Piece { piece_type: Knight, color: Color::Black }.to_fen_char(), 'n');
+        assert_eq!(PieceType::from_char('x'), None);
+        assert_eq!(Piece::from_fen_char('1'), None);
+    }
 
     #[test]
     fn address_parse() {
         macro_rules! check_neg {
-            ($addr:expr) => {
-                assert_eq!(Address::from_str($addr), Err(ParseAddressError));
+            ($addr:expr, $err:expr) => {
+                assert_eq!(Address::from_str($addr), Err($err));
             };
         }
 
-        check_neg!("");
-        check_neg!("a");
-        check_neg!("f11");
-        check_neg!("6e");
-        check_neg!("f9");
-        check_neg!("j5");
-        check_neg!("2");
-        check_neg!("2789");
-        check_neg!("1f");
-        check_neg!("c0");
+        use ParseAddressError::*;
+        check_neg!("", WrongLength(0));
+        check_neg!("a", WrongLength(1));
+        check_neg!("f11", WrongLength(3));
+        check_neg!("6e", BadFile('6'));
+        check_neg!("f9", BadRank('9'));
+        check_neg!("j5", BadFile('j'));
+        check_neg!("2", WrongLength(1));
+        check_neg!("2789", WrongLength(4));
+        check_neg!("1f", BadFile('1'));
+        check_neg!("c0", BadRank('0'));
+        // no trimming, the length tells about the stray whitespace
+        check_neg!("e4 ", WrongLength(3));
+        check_neg!(" e4", WrongLength(3));
+        check_neg!("ё4", BadFile('ё'));
 
         for r in '1'..='8' {
             for c in 'a'..='h' {
                 let addr_str = format!("{}{}", c, r);
                 let addr = Address::from_str(&addr_str).unwrap();
 
-                let r_int: u8 = (r as u8) - ('1' as u8);
-                let c_int: u8 = (c as u8) - ('a' as u8);
+                let r_int: u8 = (r as u8) - b'1';
+                let c_int: u8 = (c as u8) - b'a';
 
                 println!("{}: ({}, {})", addr_str, r_int, c_int);
 
@@ -403,6 +1481,21 @@ mod test {
         }
     }
 
+    #[test]
+    fn address_try_new() {
+        assert_eq!(Address::try_new(0, 0), Some(Address::parse("a1")));
+        assert_eq!(Address::try_new(7, 7), Some(Address::parse("h8")));
+        assert_eq!(Address::try_new(8, 0), None);
+        assert_eq!(Address::try_new(0, 8), None);
+        assert_eq!(Address::try_new(u8::MAX, u8::MAX), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn address_new_out_of_range() {
+        Address::new(3, 8);
+    }
+
     #[test]
     fn address_color() {
         let color = Cell::new(Color::Black);
@@ -476,9 +1569,96 @@ mod test {
         );
     }
 
+    #[test]
+    fn move_new_and_display() {
+        let mv = Move::new(Address::parse("e2"), Address::parse("e4"), None);
+        assert_eq!(mv.kind, MoveKind::Normal);
+        assert_eq!(mv.promotion(), None);
+        assert_eq!(mv.to_string(), "e2e4");
+
+        let mv = Move::new(Address::parse("a7"), Address::parse("b8"), Some(PieceType::Rook));
+        assert_eq!(mv.kind, MoveKind::Promotion(PieceType::Rook));
+        assert_eq!(mv.promotion(), Some(PieceType::Rook));
+        assert_eq!(mv.to_string(), "a7b8r");
+        assert_eq!(mv.castle_side(), None);
+    }
+
+    #[test]
+    fn recorded_move_types() {
+        // a recorded move keeps whether it took something
+        let mut board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        board.apply_uci_moves(&["e4d5"]).unwrap();
+        let mv = board.history.last().unwrap().to_move();
+        assert_eq!((mv.kind, mv.move_type), (MoveKind::Capture, MoveType::Capture));
+        board.apply_uci_moves(&["e8e7"]).unwrap();
+        assert_eq!(board.history.last().unwrap().to_move().move_type, MoveType::March);
+    }
+
+    #[test]
+    fn castle_sides() {
+        assert_eq!(Side::King.to_string(), "kingside");
+        assert_eq!(Side::Queen.to_string(), "queenside");
+
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut castles = game_engine::get_all_legal_moves_detailed(&board).into_iter()
+            .filter_map(|mv| mv.castle_side().map(|side| format!("{} {}", mv, side)))
+            .collect::<Vec<_>>();
+        castles.sort();
+        assert_eq!(castles, vec!["e1c1 queenside", "e1g1 kingside"]);
+    }
+
+    #[test]
+    fn address_ray() {
+        let squares = |from: &str, offset: (i8, i8)| -> Vec<String> {
+            Address::parse(from).ray(offset).map(|a| a.to_string()).collect()
+        };
+
+        assert_eq!(squares("e4", (0, 1)), vec!["e5", "e6", "e7", "e8"]);
+        assert_eq!(squares("c3", (-1, -1)), vec!["b2", "a1"]);
+        assert_eq!(squares("b1", (1, 2)), vec!["c3", "d5", "e7"]);
+        assert!(squares("h8", (1, 1)).is_empty());
+        assert!(squares("a1", (0, -1)).is_empty());
+
+        // every ray from every square stays on the board and covers a whole line
+        for from in (0..CELLS_COUNT).filter_map(Address::from_index) {
+            for offset in [(0, 1), (0, -1), (1, 0), (-1, 0)] {
+                for to in from.ray(offset) {
+                    assert!(to.col == from.col || to.row == from.row);
+                }
+            }
+            let line = |offsets: [(i8, i8); 2]| offsets.iter().map(|o| from.ray(*o).count()).sum::<usize>();
+            assert_eq!(line([(1, 0), (-1, 0)]), 7);
+            assert_eq!(line([(0, 1), (0, -1)]), 7);
+        }
+    }
+
+    #[test]
+    fn address_neighbors() {
+        let mut neighbors = Address::parse("a1").neighbors().map(|a| a.to_string()).collect::<Vec<_>>();
+        neighbors.sort();
+        assert_eq!(neighbors, vec!["a2", "b1", "b2"]);
+
+        assert_eq!(Address::parse("e4").neighbors().count(), 8);
+        assert_eq!(Address::parse("h5").neighbors().count(), 5);
+
+        for from in (0..CELLS_COUNT).filter_map(Address::from_index) {
+            let on_edge = |x: u8| x == 0 || x == ROW_SIZE - 1;
+            let expected = match (on_edge(from.col), on_edge(from.row)) {
+                (true, true)   => 3,
+                (false, false) => 8,
+                _              => 5,
+            };
+            assert_eq!(from.neighbors().count(), expected);
+            for to in from.neighbors() {
+                assert!(from.col.abs_diff(to.col) <= 1 && from.row.abs_diff(to.row) <= 1);
+                assert_ne!(from, to);
+            }
+        }
+    }
+
     #[test]
     fn board_index() {
-        assert_eq!(Board::get_index(Address::from_str("e4").unwrap()), 28);
+        assert_eq!(Address::from_str("e4").unwrap().to_index(), 28);
 
         let mut index = 0;
 
@@ -487,10 +1667,493 @@ mod test {
                 let addr = Address::new(c, r);
 
                 println!("{}: {}", addr, index);
-                assert_eq!(Board::get_index(addr), index);
+                assert_eq!(addr.to_index(), index);
+                assert_eq!(Address::from_index(index), Some(addr));
 
                 index += 1;
             }
         }
     }
+
+    #[test]
+    fn address_from_index_out_of_range() {
+        assert_eq!(Address::from_index(0), Some(Address::parse("a1")));
+        assert_eq!(Address::from_index(63), Some(Address::parse("h8")));
+        assert_eq!(Address::from_index(64), None);
+        assert_eq!(Address::from_index(u8::MAX), None);
+    }
+
+    #[test]
+    fn board_iter_pieces() {
+        assert_eq!(Board::new_empty().iter_pieces().count(), 0);
+
+        let board = Board::new();
+        assert_eq!(board.iter_pieces().count(), 32);
+        for (addr, piece) in board.iter_pieces() {
+            assert_eq!(*board.get_cell(addr), Some(piece));
+        }
+
+        let board = Board::from_fen("7k/8/8/8/4P3/8/8/K7 w - - 0 1").unwrap();
+        let pieces = board.iter_pieces().collect::<Vec<_>>();
+        assert_eq!(pieces, vec![
+            (Address::parse("a1"), Piece { piece_type: PieceType::King, color: Color::White }),
+            (Address::parse("e4"), Piece { piece_type: PieceType::Pawn, color: Color::White }),
+            (Address::parse("h8"), Piece { piece_type: PieceType::King, color: Color::Black }),
+        ]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn board_serde() {
+        let address = Address::parse("e4");
+        assert_eq!(serde_json::to_string(&address).unwrap(), "\"e4\"");
+        assert_eq!(serde_json::from_str::<Address>("\"e4\"").unwrap(), address);
+        assert!(serde_json::from_str::<Address>("\"e9\"").is_err());
+
+        let mut board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2").unwrap();
+        board.kill_piece(Address::parse("d5"));
+
+        let json = serde_json::to_string(&board).unwrap();
+        let restored: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.to_fen(), board.to_fen());
+        assert_eq!(restored.black_graveyard, board.black_graveyard);
+
+        assert!(serde_json::from_str::<Board>("{\"pieces\": []}").is_err());
+    }
+
+    #[test]
+    fn board_clone_is_independent() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let original = board.to_fen();
+
+        let mut trial = board.clone();
+        trial.set_cell(Address::parse("e2"), None);
+        trial.white_graveyard.push(Piece { piece_type: PieceType::Pawn, color: Color::White });
+        trial.flip_player();
+
+        assert_eq!(board.to_fen(), original);
+        assert!(board.white_graveyard.is_empty());
+        assert_ne!(trial.to_fen(), original);
+    }
+
+    #[test]
+    fn board_find_king() {
+        let board = Board::new();
+        assert_eq!(board.find_king(Color::White), Some(Address::parse("e1")));
+        assert_eq!(board.find_king(Color::Black), Some(Address::parse("e8")));
+
+        let board = Board::from_fen("8/8/8/8/8/8/8/KQQQ4 w - - 0 1").unwrap();
+        assert_eq!(board.find_king(Color::White), Some(Address::parse("a1")));
+        assert_eq!(board.find_king(Color::Black), None);
+    }
+
+    #[test]
+    fn board_piece_queries() {
+        let board = Board::new();
+        assert_eq!(board.piece_count(), 32);
+        assert_eq!(board.pieces_of(Color::White).len(), 16);
+        assert!(board.has_piece(Address::parse("e1")));
+        assert!(!board.has_piece(Address::parse("e4")));
+
+        let board = Board::from_fen("4k3/8/8/8/3p4/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.piece_count(), 4);
+        assert_eq!(board.pieces_of(Color::White), vec![Address::parse("a1"), Address::parse("e1")]);
+        assert_eq!(board.pieces_of(Color::Black), vec![Address::parse("d4"), Address::parse("e8")]);
+    }
+
+    #[test]
+    fn board_material() {
+        let board = Board::new();
+        assert_eq!(board.material_count(Color::White), 4000);
+        assert_eq!(board.material_count(Color::Black), 4000);
+        assert_eq!(board.material_balance(), 0);
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/PP6/RN2K3 w - - 0 1").unwrap();
+        assert_eq!(board.material_count(Color::White), 1020);
+        assert_eq!(board.material_count(Color::Black), 0);
+        assert_eq!(board.material_balance(), 1020);
+
+        let board = Board::from_fen("3qk3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.material_balance(), -400);
+
+        // a capturing promotion: the pawn and the rook go, the queen comes
+        let mut board = Board::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.material_balance(), 100 - 500);
+        game_engine::make_move_coords(&mut board, Address::parse("a7"), Address::parse("b8")).unwrap();
+        assert_eq!(board.material_count(Color::White), 900);
+        assert_eq!(board.material_count(Color::Black), 0);
+        board.undo_move();
+        assert_eq!(board.material_balance(), 100 - 500);
+
+        let mut board = Board::new();
+        board.remove_piece(Address::parse("d1"));
+        board.kill_piece(Address::parse("a8"));
+        assert_eq!(board.material_balance(), -900 + 500);
+    }
+
+    #[test]
+    fn board_mirror_vertical() {
+        let board = Board::from_fen("r3k2r/pp3ppp/8/3pP3/8/8/PPP2PPP/R3K3 w Qkq d6 5 12").unwrap();
+        let mirror = board.mirror_vertical();
+        assert_eq!(mirror.to_fen(), "r3k3/ppp2ppp/8/8/3Pp3/8/PP3PPP/R3K2R b KQq d3 5 12");
+        assert!(mirror.mirror_vertical() == board);
+
+        // the mirrored side can take en passant the same way
+        let moves = |board: &Board, square: &str| game_engine::get_legal_moves(board, Address::parse(square)).unwrap();
+        assert_eq!(moves(&board, "e5"), vec![Address::parse("e6"), Address::parse("d6")]);
+        assert_eq!(moves(&mirror, "e4"), vec![Address::parse("e3"), Address::parse("d3")]);
+
+        assert!(Board::new().mirror_vertical().mirror_vertical() == Board::new());
+        assert_eq!(Board::new().mirror_vertical().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1");
+
+        let mut played = Board::new();
+        played.apply_uci_moves(&["e2e4", "d7d5", "e4d5"]).unwrap();
+        let mirror = played.mirror_vertical();
+        assert_eq!(mirror.white_graveyard, vec![Piece { piece_type: PieceType::Pawn, color: Color::White }]);
+        assert!(mirror.black_graveyard.is_empty());
+        assert!(mirror.history.is_empty());
+    }
+
+    #[test]
+    fn board_apply_uci_moves() {
+        let mut board = Board::new();
+        assert_eq!(board.apply_uci_moves(&["e2e4", "e7e5", "g1f3"]), Ok(()));
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        let mut board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.apply_uci_moves(&["a7a8n"]), Ok(()));
+        assert_eq!(*board.get_cell(Address::parse("a8")), Some(Piece { piece_type: PieceType::Knight, color: Color::White }));
+
+        // stops at the first bad move and keeps what was played before it
+        let mut board = Board::new();
+        assert_eq!(
+            board.apply_uci_moves(&["e2e4", "e7e5", "e4e5", "g1f3"]),
+            Err(MoveError::UnreachableMove { from: Address::parse("e4"), to: Address::parse("e5") })
+        );
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+        assert_eq!(board.apply_uci_moves(&["e2"]), Err(MoveError::InvalidAddress(ParseAddressError::WrongLength(2))));
+        assert_eq!(board.apply_uci_moves(&[]), Ok(()));
+    }
+
+    #[test]
+    fn board_auto_flip() {
+        // the first board line after the graveyard starts with the top rank
+        let top_rank = |board: &Board| board.to_string().lines().nth(2).unwrap().chars().next().unwrap();
+
+        let mut board = Board::new();
+        board.set_auto_flip(true);
+        assert_eq!(top_rank(&board), '8');
+
+        assert!(game_engine::make_move_coords(&mut board, Address::parse("e2"), Address::parse("e4")).is_ok());
+        assert!(board.is_flipped());
+        assert_eq!(top_rank(&board), '1');
+
+        assert!(game_engine::make_move_coords(&mut board, Address::parse("e7"), Address::parse("e5")).is_ok());
+        assert_eq!(top_rank(&board), '8');
+
+        // the manual orientation survives auto-flip and comes back when it is off
+        board.set_auto_flip(false);
+        board.flip_board_for(Color::Black);
+        board.set_auto_flip(true);
+        assert!(!board.is_flipped());
+        board.set_auto_flip(false);
+        assert!(board.is_flipped());
+
+        // a manual flip turns from what is shown and takes over
+        board.set_auto_flip(true);
+        board.flip_board();
+        assert!(!board.auto_flip);
+        assert!(board.is_flipped());
+        assert!(game_engine::make_move_coords(&mut board, Address::parse("g1"), Address::parse("f3")).is_ok());
+        assert!(board.is_flipped());
+    }
+
+    #[test]
+    fn board_render_ascii() {
+        let mut board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        board.kill_piece(Address::parse("a7"));
+
+        assert_eq!(board.render_ascii(Color::White), "\
+Black captured:
+    a b c d e f g h
+  +-----------------+
+8 | r n b q k b n r | 8
+7 | . p p p p p p p | 7
+6 | . . . . . . . . | 6
+5 | . . . . . . . . | 5
+4 | . . . . P . . . | 4
+3 | . . . . . . . . | 3
+2 | P P P P . P P P | 2
+1 | R N B Q K B N R | 1
+  +-----------------+
+    a b c d e f g h
+White captured: p");
+
+        assert_eq!(board.render_ascii(Color::Black), "\
+White captured: p
+    h g f e d c b a
+  +-----------------+
+1 | R N B K Q B N R | 1
+2 | P P P . P P P P | 2
+3 | . . . . . . . . | 3
+4 | . . . P . . . . | 4
+5 | . . . . . . . . | 5
+6 | . . . . . . . . | 6
+7 | p p p p p p p . | 7
+8 | r n b k q b n r | 8
+  +-----------------+
+    h g f e d c b a
+Black captured:");
+    }
+
+    #[test]
+    fn board_from_fen() {
+        let start = Board::new();
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+        assert_eq!(board.pieces, start.pieces);
+        assert_eq!(board.whose_turn, Color::White);
+        assert_eq!(board.castling_rights, [true; 4]);
+        assert_eq!(board.en_passant_target, None);
+
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 0 3").unwrap();
+        assert_eq!(board.castling_rights, [true, false, false, true]);
+        assert_eq!(board.en_passant_target, Some(Address::parse("d6")));
+        assert_eq!(
+            *board.get_cell(Address::parse("e5")),
+            Some(Piece { piece_type: PieceType::Pawn, color: Color::White })
+        );
+        assert_eq!(
+            *board.get_cell(Address::parse("d5")),
+            Some(Piece { piece_type: PieceType::Pawn, color: Color::Black })
+        );
+
+        let board = Board::from_fen("8/8/8/8/8/8/8/4K2k b - -").unwrap();
+        assert_eq!(board.whose_turn, Color::Black);
+        assert_eq!(board.castling_rights, [false; 4]);
+    }
+
+    #[test]
+    fn board_from_fen_errors() {
+        macro_rules! check_err {
+            ($fen:expr, $err:expr) => {
+                assert_eq!(Board::from_fen($fen).err(), Some($err));
+            };
+        }
+
+        check_err!("8/8/8/8/8/8/8/8 w", FenError::WrongFieldCount(2));
+        check_err!("8/8/8/8/8/8/8 w - - 0 1", FenError::WrongRankCount(7));
+        check_err!("8/8/8/8/8/8/8/7 w - - 0 1", FenError::WrongRankSize { rank: 1, squares: 7 });
+        check_err!("ppppppppp/8/8/8/8/8/8/8 w - - 0 1", FenError::WrongRankSize { rank: 8, squares: 9 });
+        check_err!("8/8/8/8/8/8/8/7x w - - 0 1", FenError::IllegalCharacter('x'));
+        check_err!("8/8/8/8/8/8/8/8 x - - 0 1", FenError::InvalidActiveColor("x".to_string()));
+        check_err!("8/8/8/8/8/8/8/8 w KX - 0 1", FenError::InvalidCastling("KX".to_string()));
+        check_err!("8/8/8/8/8/8/8/8 w - e9 0 1", FenError::InvalidEnPassant("e9".to_string()));
+        check_err!("8/8/8/8/8/8/8/8 w - - x 1", FenError::InvalidCounter("x".to_string()));
+    }
+
+    #[test]
+    fn board_from_ascii() {
+        let board = Board::from_ascii("
+            r . b q k b n r
+            p p p p . p p p
+            . . n . . . . .
+            . . . . p . . .
+            . . . . P . . .
+            . . . . . N . .
+            P P P P . P P P
+            R N B Q K B . R
+        ").unwrap();
+        assert_eq!(board.to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w - - 0 1");
+
+        // what Display and render_ascii print reads back
+        let start = Board::new();
+        assert_eq!(Board::from_ascii(&start.to_string()).unwrap().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+        let mut board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Board::from_ascii(&board.render_ascii(Color::White)).unwrap().to_fen(), board.to_fen());
+
+        // graveyard lines of Display are not ranks
+        board.kill_piece(Address::parse("d5"));
+        board.kill_piece(Address::parse("e4"));
+        assert_eq!(Board::from_ascii(&board.to_string()).unwrap().to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn board_from_ascii_errors() {
+        assert_eq!(Board::from_ascii(". . . .").err(), Some(FenError::WrongRankCount(1)));
+        assert_eq!(
+            Board::from_ascii("8\n8\n8\n8\n8\n8\n8\n8").err(),
+            Some(FenError::WrongRankCount(0))
+        );
+
+        let diagram = "........\n........\n.......\n........\n........\n........\n........\n........";
+        assert_eq!(Board::from_ascii(diagram).err(), Some(FenError::WrongRankSize { rank: 6, squares: 7 }));
+
+        let diagram = "........\n........\n........\n....x...\n........\n........\n........\n........";
+        assert_eq!(Board::from_ascii(diagram).err(), Some(FenError::IllegalCharacter('x')));
+    }
+
+    #[test]
+    fn board_chess960() {
+        // the castling rooks are written by their files
+        let board = Board::new_chess960(518).unwrap();
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+        assert_eq!(board.castling_rook_cols, [7, 0]);
+
+        assert_eq!(Board::new_chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+        assert_eq!(Board::new_chess960(959).unwrap().to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1");
+        assert!(Board::new_chess960(960).is_none());
+
+        let mut seen = std::collections::HashSet::new();
+        for n in 0..960 {
+            let board = Board::new_chess960(n).unwrap();
+            let rank = (0..ROW_SIZE).map(|c| board.get_cell(Address::new(c, 0)).unwrap().piece_type).collect::<Vec<_>>();
+            let cols_of = |piece_type: PieceType| (0..ROW_SIZE).filter(|c| rank[*c as usize] == piece_type).collect::<Vec<_>>();
+
+            let bishops = cols_of(PieceType::Bishop);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+
+            let rooks = cols_of(PieceType::Rook);
+            let king = cols_of(PieceType::King)[0];
+            assert!(rooks[0] < king && king < rooks[1]);
+            assert_eq!(board.castling_rook_cols, [rooks[1], rooks[0]]);
+
+            assert!(seen.insert(rank.iter().map(|t| t.to_char()).collect::<String>()));
+
+            let parsed = Board::from_fen(&board.to_fen()).unwrap();
+            assert!(parsed == board, "{}", board.to_fen());
+            assert!(parsed.chess960);
+        }
+    }
+
+    #[test]
+    fn fen_chess960_castling() {
+        let board = Board::from_fen("rkr5/8/8/8/8/8/8/RKR5 w Ca - 0 1").unwrap();
+        assert!(board.chess960);
+        assert_eq!(board.castling_rights, [true, false, false, true]);
+        assert_eq!(board.castling_rook_cols, [2, 0]);
+        assert_eq!(board.to_fen(), "rkr5/8/8/8/8/8/8/RKR5 w Ca - 0 1");
+
+        // the rights a rook has lost are left out, the others keep their files
+        let mut board = Board::new_chess960(100).unwrap();
+        board.castling_rights[WHITE_QUEEN_SIDE] = false;
+        let parsed = Board::from_fen(&board.to_fen()).unwrap();
+        assert!(parsed == board);
+        assert_eq!(parsed.castling_rook_cols, board.castling_rook_cols);
+
+        // standard letters still mean the a and h rooks
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(!board.chess960);
+        assert_eq!(board.castling_rook_cols, [7, 0]);
+
+        let invalid = |fen: &str| Board::from_fen(fen).err();
+        // the c1 rook is on the queen side of an f1 king
+        assert_eq!(Board::from_fen("rkr5/8/8/8/8/8/8/R1R2K2 w C - 0 1").unwrap().castling_rook_cols, [7, 2]);
+        // no king on its home rank to tell the side, two rooks for one side
+        assert_eq!(invalid("rkr5/8/8/8/8/8/1K6/R1R5 w C - 0 1"), Some(FenError::InvalidCastling("C".to_string())));
+        assert_eq!(invalid("rkr5/8/8/8/8/8/8/RKR5 w CD - 0 1"), Some(FenError::InvalidCastling("CD".to_string())));
+        assert_eq!(invalid("rkr5/8/8/8/8/8/8/RKR5 w X - 0 1"), Some(FenError::InvalidCastling("X".to_string())));
+    }
+
+    #[test]
+    fn board_set_and_remove_piece() {
+        let white_king = Piece { piece_type: PieceType::King, color: Color::White };
+        let black_rook = Piece { piece_type: PieceType::Rook, color: Color::Black };
+
+        let mut board = Board::new_empty();
+        board.set_piece(Address::parse("e1"), Some(white_king));
+        board.set_piece(Address::parse("a8"), Some(black_rook));
+        board.set_piece(Address::parse("h8"), Some(black_rook));
+        assert_eq!(board.to_fen(), "r6r/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(board.find_king(Color::White), Some(Address::parse("e1")));
+
+        // replacing and removing leaves no trace in the graveyards or the bitboards
+        board.set_piece(Address::parse("h8"), Some(white_king));
+        assert_eq!(board.remove_piece(Address::parse("a8")), Some(black_rook));
+        assert_eq!(board.remove_piece(Address::parse("a8")), None);
+        board.set_piece(Address::parse("h8"), None);
+        assert!(board.black_graveyard.is_empty());
+        assert_eq!(board.piece_count(), 1);
+        assert_eq!(board.occupied(), bitboard::of(Address::parse("e1")));
+
+        // setup does not touch the rights or the clocks
+        let mut board = Board::new();
+        board.halfmove_clock = 7;
+        board.remove_piece(Address::parse("h1"));
+        board.remove_piece(Address::parse("e2"));
+        assert_eq!(board.castling_rights, [true; 4]);
+        assert_eq!(board.halfmove_clock, 7);
+        assert_eq!(board.en_passant_target, None);
+    }
+
+    #[test]
+    fn board_set_turn() {
+        let mut board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        let hash = board.zobrist_hash();
+
+        board.set_turn(Color::Black);
+        assert_eq!(board.en_passant_target, Some(Address::parse("e3")));
+        assert_eq!(board.zobrist_hash(), hash);
+
+        board.set_turn(Color::White);
+        assert_eq!(board.whose_turn, Color::White);
+        assert_eq!(board.en_passant_target, None);
+        assert_ne!(board.zobrist_hash(), hash);
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+
+        // a played move cannot be taken back across the change
+        let mut board = Board::new();
+        board.apply_uci_moves(&["e2e4", "e7e5"]).unwrap();
+        assert!(board.undo_move());
+        board.set_turn(Color::White);
+        assert!(board.history.is_empty());
+        assert!(!board.undo_move());
+        assert!(!board.redo_move());
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn board_eq() {
+        assert!(Board::new() == Board::new());
+        assert!(Board::new() == Board::from_fen(&Board::new().to_fen()).unwrap());
+        assert!(Board::new() != Board::new_empty());
+
+        // the same position reached by play, history and display settings aside
+        let mut played = Board::new();
+        game_engine::make_moves(&mut played, vec![("e2", "e4"), ("d7", "d5"), ("e4", "d5")]).unwrap();
+        let mut loaded = Board::from_fen("rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2").unwrap();
+        assert!(played == loaded);
+        loaded.flip_board();
+        assert!(played == loaded);
+
+        let differs = |f: &dyn Fn(&mut Board)| {
+            let mut board = played.clone();
+            f(&mut board);
+            board != played
+        };
+        assert!(differs(&|b| b.whose_turn = Color::White));
+        assert!(differs(&|b| b.castling_rights[BLACK_QUEEN_SIDE] = false));
+        assert!(differs(&|b| b.en_passant_target = Some(Address::parse("e3"))));
+        assert!(differs(&|b| b.halfmove_clock = 3));
+        assert!(differs(&|b| b.fullmove_number = 9));
+        assert!(differs(&|b| b.set_cell(Address::parse("a1"), None)));
+        assert!(!differs(&|b| b.white_graveyard.clear()));
+    }
+
+    #[test]
+    fn board_to_fen() {
+        assert_eq!(Board::new().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 0 3",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 b - - 37 92",
+        ];
+
+        for fen in fens {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
 }
\ No newline at end of file
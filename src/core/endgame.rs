@@ -0,0 +1,286 @@
+use std::sync::OnceLock;
+
+use super::bitboard::{self, Bitboard};
+use super::chess_types::*;
+use super::game_engine;
+
+// a move for the side to move with a king and a queen or a rook against a lone king,
+// None for any other material. The moves come from a table of every such position built
+// backwards from the mates on first use, so each one pushes the defending king towards
+// the edge by the shortest way there is and the mate always comes
+pub fn drive_mate(board: &Board) -> Option<Move> {
+    let attacker = board.whose_turn;
+    let piece = get_mating_piece(board, attacker)?;
+    let piece_type = board.get_cell(piece).map(|piece| piece.piece_type)?;
+    let strong_king = board.find_king(attacker)?;
+    let weak_king = board.find_king(attacker.opposite())?;
+
+    let table = MateTable::get(piece_type);
+    let moves_to_mate = table.strong[get_index(strong_king, piece, weak_king)];
+    if moves_to_mate == UNKNOWN {
+        return None;
+    }
+
+    game_engine::get_all_legal_moves_detailed(board).into_iter()
+        .filter(|mv| !matches!(mv.kind, MoveKind::Castle(_)))
+        .find(|mv| {
+            let index = if mv.from == strong_king {
+                get_index(mv.to, piece, weak_king)
+            } else {
+                get_index(strong_king, mv.to, weak_king)
+            };
+            table.weak[index] == moves_to_mate - 1
+        })
+}
+
+// number of moves the strong side needs to mate with best play from a position,
+// or None when drive_mate does not know it
+pub fn moves_to_mate(board: &Board) -> Option<u32> {
+    let attacker = board.whose_turn;
+    let piece = get_mating_piece(board, attacker)?;
+    let piece_type = board.get_cell(piece).map(|piece| piece.piece_type)?;
+    let index = get_index(board.find_king(attacker)?, piece, board.find_king(attacker.opposite())?);
+
+    match MateTable::get(piece_type).strong[index] {
+        UNKNOWN => None,
+        moves => Some(moves as u32),
+    }
+}
+
+// the queen or the rook of the color when that is all the material besides the kings
+fn get_mating_piece(board: &Board, color: Color) -> Option<Address> {
+    let mut res = None;
+
+    for (address, piece) in board.iter_pieces() {
+        match piece.piece_type {
+            PieceType::King => continue,
+            PieceType::Queen | PieceType::Rook if piece.color == color && res.is_none() => res = Some(address),
+            _ => return None,
+        }
+    }
+
+    res
+}
+
+// no mate is known, the position is drawn, illegal or not reached yet
+const UNKNOWN: u8 = u8::MAX;
+const POSITIONS_COUNT: usize = CELLS_COUNT as usize * CELLS_COUNT as usize * CELLS_COUNT as usize;
+
+// moves the strong side needs to mate, indexed by get_index:
+// `strong` for positions with the strong side to move, `weak` for the lone king to move
+// where 0 means it is mated
+struct MateTable {
+    strong: Vec<u8>,
+    weak: Vec<u8>,
+}
+
+impl MateTable {
+    fn get(piece_type: PieceType) -> &'static MateTable {
+        static QUEEN: OnceLock<MateTable> = OnceLock::new();
+        static ROOK: OnceLock<MateTable> = OnceLock::new();
+
+        match piece_type {
+            PieceType::Queen => QUEEN.get_or_init(|| MateTable::build(PieceType::Queen)),
+            _                => ROOK.get_or_init(|| MateTable::build(PieceType::Rook)),
+        }
+    }
+
+    // retrograde analysis: starting from the mates, a strong side position is won in n
+    // once one of its moves reaches a lost position for the weak king in n-1,
+    // and a weak king position is lost in n once every one of its moves reaches
+    // a won position, the last of them found being the longest defence
+    fn build(piece_type: PieceType) -> MateTable {
+        let piece = Piece { piece_type, color: Color::White };
+        let mut res = MateTable { strong: vec![UNKNOWN; POSITIONS_COUNT], weak: vec![UNKNOWN; POSITIONS_COUNT] };
+        // the weak king moves not yet known to lose, None when it can escape by taking the piece
+        let mut moves_left = vec![None; POSITIONS_COUNT];
+        let mut is_check = vec![false; POSITIONS_COUNT];
+        let mut lost = Vec::new();
+
+        for (strong_king, piece_address, weak_king) in get_positions() {
+            let index = get_index(strong_king, piece_address, weak_king);
+            let (moves, can_take, is_in_check) = get_weak_king_moves(piece, strong_king, piece_address, weak_king);
+            is_check[index] = is_in_check;
+
+            if can_take {
+                continue;
+            }
+            moves_left[index] = Some(moves.count_ones());
+
+            if moves == 0 && is_in_check {
+                res.weak[index] = 0;
+                lost.push((strong_king, piece_address, weak_king));
+            }
+        }
+
+        let mut moves_to_mate = 0;
+        while !lost.is_empty() {
+            moves_to_mate += 1;
+
+            // the strong side moves that reach the lost positions, played backwards
+            let mut won = Vec::new();
+            for (strong_king, piece_address, weak_king) in lost.drain(..) {
+                let occupied = bitboard::of(strong_king) | bitboard::of(piece_address) | bitboard::of(weak_king);
+
+                let king_origins = bitboard::king_attacks(strong_king) & !occupied & !bitboard::king_attacks(weak_king);
+                let piece_origins = game_engine::get_piece_attacks(piece, piece_address, occupied) & !occupied;
+
+                let origins = bitboard::addresses(king_origins).map(|from| (from, piece_address))
+                    .chain(bitboard::addresses(piece_origins).map(|from| (strong_king, from)));
+
+                for (strong_king, piece_address) in origins {
+                    let index = get_index(strong_king, piece_address, weak_king);
+                    // the weak king may not stand in check with the strong side to move
+                    if res.strong[index] == UNKNOWN && !is_check[index] {
+                        res.strong[index] = moves_to_mate;
+                        won.push((strong_king, piece_address, weak_king));
+                    }
+                }
+            }
+
+            // the weak king moves into the won positions, played backwards
+            for (strong_king, piece_address, weak_king) in won {
+                let occupied = bitboard::of(strong_king) | bitboard::of(piece_address);
+                let origins = bitboard::king_attacks(weak_king) & !occupied & !bitboard::king_attacks(strong_king);
+
+                for weak_king in bitboard::addresses(origins) {
+                    let index = get_index(strong_king, piece_address, weak_king);
+                    if let Some(moves) = moves_left[index].as_mut() {
+                        *moves -= 1;
+                        if *moves == 0 {
+                            res.weak[index] = moves_to_mate;
+                            lost.push((strong_king, piece_address, weak_king));
+                        }
+                    }
+                }
+            }
+        }
+
+        res
+    }
+}
+
+fn get_index(strong_king: Address, piece: Address, weak_king: Address) -> usize {
+    let cells = CELLS_COUNT as usize;
+    (strong_king.to_index() as usize * cells + piece.to_index() as usize) * cells + weak_king.to_index() as usize
+}
+
+// every placement of the three pieces on distinct squares with the kings apart
+fn get_positions() -> impl Iterator<Item = (Address, Address, Address)> {
+    let squares = || (0..CELLS_COUNT).filter_map(Address::from_index);
+
+    squares()
+        .flat_map(move |strong_king| squares().map(move |piece| (strong_king, piece)))
+        .flat_map(move |(strong_king, piece)| squares().map(move |weak_king| (strong_king, piece, weak_king)))
+        .filter(|&(strong_king, piece, weak_king)| {
+            strong_king != piece && piece != weak_king
+                && strong_king != weak_king
+                && !bitboard::contains(bitboard::king_attacks(strong_king), weak_king)
+        })
+}
+
+// squares the weak king can step to without taking the piece, whether it can take it
+// and whether it is in check
+fn get_weak_king_moves(piece: Piece, strong_king: Address, piece_address: Address, weak_king: Address) -> (Bitboard, bool, bool) {
+    // the squares behind the weak king along the piece's lines are attacked too
+    let occupied = bitboard::of(strong_king) | bitboard::of(piece_address);
+    let piece_attacks = game_engine::get_piece_attacks(piece, piece_address, occupied);
+    let attacked = bitboard::king_attacks(strong_king) | piece_attacks;
+
+    let targets = bitboard::king_attacks(weak_king) & !attacked;
+    let can_take = bitboard::contains(targets, piece_address);
+
+    (targets & !bitboard::of(piece_address), can_take, bitboard::contains(piece_attacks, weak_king))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn play(board: &mut Board, mv: Move) {
+        game_engine::make_move_unchecked_promoting(board, mv.from, mv.to, PieceType::Queen);
+    }
+
+    // plays drive_mate against `defend` until mate, returning the number of attacking moves,
+    // or None when it gives up or takes more than 50 moves
+    fn mate_length(fen: &str, defend: &mut impl FnMut(&Board, Vec<Move>) -> Move) -> Option<u32> {
+        let mut board = Board::from_fen(fen).unwrap();
+        let attacker = board.whose_turn;
+
+        for moves in 1..=50 {
+            let mv = drive_mate(&board)?;
+            play(&mut board, mv);
+            if game_engine::is_checkmate(&board, attacker.opposite()) {
+                return Some(moves);
+            }
+
+            let replies = game_engine::get_all_legal_moves_detailed(&board);
+            assert!(!replies.is_empty(), "stalemate in {}", board.to_fen());
+            let reply = defend(&board, replies);
+            play(&mut board, reply);
+        }
+
+        None
+    }
+
+    #[test]
+    fn drive_mate_only_for_known_endings() {
+        assert!(drive_mate(&Board::new()).is_none());
+        assert!(drive_mate(&Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap()).is_none());
+        assert!(drive_mate(&Board::from_fen("4k3/8/8/8/8/8/4P3/3QK3 w - - 0 1").unwrap()).is_none());
+        assert!(drive_mate(&Board::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").unwrap()).is_none());
+        // the side with the rook is not to move
+        assert!(drive_mate(&Board::from_fen("4k3/8/8/8/8/8/8/3RK3 b - - 0 1").unwrap()).is_none());
+    }
+
+    #[test]
+    fn mate_table_lengths() {
+        // the longest mates are known: 10 moves with a queen and 16 with a rook
+        let longest = |piece_type| MateTable::get(piece_type).strong.iter().filter(|&&moves| moves != UNKNOWN).max().copied();
+        assert_eq!(longest(PieceType::Queen), Some(10));
+        assert_eq!(longest(PieceType::Rook), Some(16));
+
+        assert_eq!(moves_to_mate(&Board::from_fen("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1").unwrap()), Some(1));
+        assert_eq!(moves_to_mate(&Board::from_fen("6k1/8/6K1/8/8/8/8/R7 w - - 0 1").unwrap()), Some(1));
+        assert_eq!(moves_to_mate(&Board::from_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap()), Some(14));
+        assert_eq!(moves_to_mate(&Board::new()), None);
+    }
+
+    #[test]
+    fn drive_mate_delivers_mate() {
+        // xorshift so the defences are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut random_defence = |_: &Board, replies: Vec<Move>| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            replies[state as usize % replies.len()]
+        };
+        // the reply after which the mate takes the longest
+        let mut stubborn_defence = |board: &Board, replies: Vec<Move>| {
+            let mut board = board.clone();
+            replies.into_iter()
+                .max_by_key(|&reply| {
+                    play(&mut board, reply);
+                    let moves = moves_to_mate(&board);
+                    board.unmake_move();
+                    moves
+                })
+                .unwrap()
+        };
+
+        let positions = [
+            ("8/8/8/3k4/8/8/8/2Q1K3 w - - 0 1", 8),
+            ("8/8/8/3k4/8/8/8/R3K3 w - - 0 1", 14),
+            ("8/8/8/8/4k3/8/8/K6R w - - 0 1", 15),
+            ("7K/8/8/8/3k4/8/8/q7 b - - 0 1", 4),
+            ("K7/8/8/8/8/3k4/8/7r b - - 0 1", 6),
+        ];
+
+        for (fen, moves) in positions {
+            assert_eq!(moves_to_mate(&Board::from_fen(fen).unwrap()), Some(moves), "{}", fen);
+            assert_eq!(mate_length(fen, &mut stubborn_defence), Some(moves), "{}", fen);
+            assert!(mate_length(fen, &mut random_defence).is_some_and(|length| length <= moves), "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use super::bitboard::Bitboard;
+use super::chess_types::*;
+use super::game_engine;
+
+// tunable evaluation parameters, all in centipawns except the material percentage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalWeights {
+    // percent of the piece values counted
+    pub material_scale: i32,
+    // per legal move more than the opponent
+    pub mobility_weight: i32,
+    pub passed_pawn_bonus: i32,
+    pub rook_open_file_bonus: i32,
+    // penalty per square next to the king the opponent attacks
+    pub king_safety_weight: i32,
+    // penalties per pawn counted by doubled_pawns and isolated_pawns
+    pub doubled_pawn_penalty: i32,
+    pub isolated_pawn_penalty: i32,
+    // penalties per piece found by is_bad_bishop and is_knight_on_rim
+    pub bad_bishop_penalty: i32,
+    pub knight_on_rim_penalty: i32,
+    // per attacker more than the opponent on d4, e4, d5 and e5, summed over the four
+    pub center_control_weight: i32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights {
+            material_scale: 100,
+            mobility_weight: 4,
+            passed_pawn_bonus: 20,
+            rook_open_file_bonus: 15,
+            king_safety_weight: 5,
+            doubled_pawn_penalty: 15,
+            isolated_pawn_penalty: 10,
+            bad_bishop_penalty: 10,
+            knight_on_rim_penalty: 5,
+            center_control_weight: 3,
+        }
+    }
+}
+
+// from [a1..a8] to [h1..h8], given for white, mirrored by rank for black
+static PAWN_SQUARE_BONUS: BoardLayer<i32> = [
+     0,  0,  0,   0,   0,  0,  0,  0,
+     5,  5,  5,  -5,  -5,  5,  5,  5,
+     0,  0,  5,  10,  10,  5,  0,  0,
+     0,  0, 10,  20,  20, 10,  0,  0,
+     5,  5, 10,  20,  20, 10,  5,  5,
+    10, 10, 15,  25,  25, 15, 10, 10,
+    30, 30, 30,  30,  30, 30, 30, 30,
+     0,  0,  0,   0,   0,  0,  0,  0,
+];
+
+static KNIGHT_SQUARE_BONUS: BoardLayer<i32> = [
+    -30, -20, -10, -10, -10, -10, -20, -30,
+    -20,   0,   0,   5,   5,   0,   0, -20,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   5,  10,  15,  15,  10,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -20,   0,   0,   0,   0,   0,   0, -20,
+    -30, -20, -10, -10, -10, -10, -20, -30,
+];
+
+static BISHOP_SQUARE_BONUS: BoardLayer<i32> = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+static ROOK_SQUARE_BONUS: BoardLayer<i32> = [
+     0,  0,  0,  5,  5,  0,  0,  0,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     5, 10, 10, 10, 10, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+static QUEEN_SQUARE_BONUS: BoardLayer<i32> = [
+    -20, -10, -10, -5, -5, -10, -10, -20,
+    -10,   0,   5,  0,  0,   0,   0, -10,
+    -10,   5,   5,  5,  5,   5,   0, -10,
+      0,   0,   5,  5,  5,   5,   0,  -5,
+     -5,   0,   5,  5,  5,   5,   0,  -5,
+    -10,   0,   5,  5,  5,   5,   0, -10,
+    -10,   0,   0,  0,  0,   0,   0, -10,
+    -20, -10, -10, -5, -5, -10, -10, -20,
+];
+
+// the king hides behind its pawns until the endgame, then it heads for the center
+static KING_SQUARE_BONUS: BoardLayer<i32> = [
+     20,  30,  10,   0,   0,  10,  30,  20,
+     20,  20,   0,   0,   0,   0,  20,  20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+];
+
+static KING_ENDGAME_SQUARE_BONUS: BoardLayer<i32> = [
+    -50, -30, -30, -30, -30, -30, -30, -50,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -50, -40, -30, -20, -20, -30, -40, -50,
+];
+
+// d4, e4, d5 and e5
+static CENTER: Bitboard = 0x0000_0018_1800_0000;
+
+// centipawn score from white's point of view with the default weights
+pub fn evaluate(board: &Board) -> i32 {
+    evaluate_with(board, &EvalWeights::default())
+}
+
+pub fn evaluate_with(board: &Board, weights: &EvalWeights) -> i32 {
+    let mut score = 0;
+    let phase = game_engine::detect_phase(board);
+    let occupied = board.occupied();
+
+    // each term is looked at only when it weighs something, this runs at every search leaf
+    for (addr, piece) in board.iter_pieces() {
+        let mut value = get_material(&piece, phase) * weights.material_scale / 100 + get_square_bonus(&piece, addr, phase);
+
+        match piece.piece_type {
+            PieceType::Pawn if weights.passed_pawn_bonus != 0 && game_engine::is_passed_pawn(board, addr)         => value += weights.passed_pawn_bonus,
+            PieceType::Rook if weights.rook_open_file_bonus != 0 && is_on_open_file(board, addr)                  => value += weights.rook_open_file_bonus,
+            PieceType::Bishop if weights.bad_bishop_penalty != 0 && game_engine::is_bad_bishop(board, addr)       => value -= weights.bad_bishop_penalty,
+            PieceType::Knight if weights.knight_on_rim_penalty != 0 && game_engine::is_knight_on_rim(board, addr) => value -= weights.knight_on_rim_penalty,
+            PieceType::King if weights.king_safety_weight != 0                                                    => value -= get_king_danger(board, addr, piece.color) * weights.king_safety_weight,
+            _                                                                                                     => {},
+        }
+
+        // the center squares the piece attacks, summed up it is the control of the center
+        if weights.center_control_weight != 0 {
+            let attacks = game_engine::get_piece_attacks(piece, addr, occupied) & CENTER;
+            value += attacks.count_ones() as i32 * weights.center_control_weight;
+        }
+
+        score += if piece.color == Color::White { value } else { -value };
+    }
+
+    let white_mobility = game_engine::mobility(board, Color::White) as i32;
+    let black_mobility = game_engine::mobility(board, Color::Black) as i32;
+    score += (white_mobility - black_mobility) * weights.mobility_weight;
+
+    for (color, sign) in [(Color::White, 1), (Color::Black, -1)] {
+        if weights.doubled_pawn_penalty != 0 {
+            score -= sign * game_engine::doubled_pawns(board, color) as i32 * weights.doubled_pawn_penalty;
+        }
+        if weights.isolated_pawn_penalty != 0 {
+            score -= sign * game_engine::isolated_pawns(board, color) as i32 * weights.isolated_pawn_penalty;
+        }
+    }
+
+    score
+}
+
+fn is_on_open_file(board: &Board, address: Address) -> bool {
+    game_engine::file_status(board, address.col) == game_engine::FileStatus::Open
+}
+
+// squares around the king the opponent attacks
+fn get_king_danger(board: &Board, king: Address, color: Color) -> i32 {
+    king.neighbors()
+        .filter(|addr| game_engine::is_square_attacked(board, *addr, color.opposite()))
+        .count() as i32
+}
+
+// the king is priceless, so it stays out of the material sum
+fn get_material(piece: &Piece, phase: GamePhase) -> i32 {
+    if piece.piece_type == PieceType::King {
+        return 0;
+    }
+
+    piece.piece_type.get_value(phase) as i32
+}
+
+fn get_square_bonus(piece: &Piece, address: Address, phase: GamePhase) -> i32 {
+    let table = match piece.piece_type {
+        PieceType::Pawn   => &PAWN_SQUARE_BONUS,
+        PieceType::Knight => &KNIGHT_SQUARE_BONUS,
+        PieceType::Bishop => &BISHOP_SQUARE_BONUS,
+        PieceType::Rook   => &ROOK_SQUARE_BONUS,
+        PieceType::Queen  => &QUEEN_SQUARE_BONUS,
+        PieceType::King if phase == GamePhase::Endgame => &KING_ENDGAME_SQUARE_BONUS,
+        PieceType::King   => &KING_SQUARE_BONUS,
+    };
+
+    let row = if piece.color == Color::White { address.row } else { ROW_SIZE - 1 - address.row };
+    table[Address::new(address.col, row).to_index() as usize]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate_symmetric_start() {
+        assert_eq!(evaluate(&Board::new()), 0);
+    }
+
+    #[test]
+    fn evaluate_material() {
+        let board = Board::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(evaluate(&board) > 800);
+
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1").unwrap();
+        assert!(evaluate(&board) < -800);
+
+        // lone kings are level
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&board), 0);
+    }
+
+    #[test]
+    fn evaluate_weights() {
+        let weights = EvalWeights::default();
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        assert_eq!(evaluate_with(&board, &weights), evaluate(&board));
+
+        // an extra knight is worth half as much at half the scale, the rim aside
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").unwrap();
+        let no_mobility = EvalWeights { mobility_weight: 0, knight_on_rim_penalty: 0, ..weights };
+        let half = EvalWeights { material_scale: 50, ..no_mobility };
+        let knight_bonus = get_square_bonus(&Piece { piece_type: PieceType::Knight, color: Color::White }, Address::parse("d1"), GamePhase::Endgame);
+        assert_eq!(evaluate_with(&board, &no_mobility) - knight_bonus, 290);
+        assert_eq!(evaluate_with(&board, &half) - knight_bonus, 145);
+
+        // the white a-pawn is passed, the black h-pawn is not
+        let board = Board::from_fen("4k3/7p/8/8/P7/6P1/8/4K3 w - - 0 1").unwrap();
+        let passed = EvalWeights { passed_pawn_bonus: 50, ..weights };
+        let not_passed = EvalWeights { passed_pawn_bonus: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &passed) - evaluate_with(&board, &not_passed), 50);
+
+        // the a1 rook stands on an open file, the h1 one behind its own pawn does not count
+        let board = Board::from_fen("4k3/8/8/8/8/8/7P/R3K2R w - - 0 1").unwrap();
+        let open_file = EvalWeights { rook_open_file_bonus: 30, ..weights };
+        let no_open_file = EvalWeights { rook_open_file_bonus: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &open_file) - evaluate_with(&board, &no_open_file), 30);
+
+        // the white king has two attacked squares next to it
+        let board = Board::from_fen("3rk3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let king_safety = EvalWeights { king_safety_weight: 10, ..weights };
+        let no_king_safety = EvalWeights { king_safety_weight: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &king_safety) - evaluate_with(&board, &no_king_safety), -20);
+
+        // doubled and isolated white c-pawns against a healthy black pair
+        let board = Board::from_fen("4k3/3pp3/8/8/2P5/2P5/8/4K3 w - - 0 1").unwrap();
+        let pawns = EvalWeights { doubled_pawn_penalty: 10, isolated_pawn_penalty: 15, ..weights };
+        let no_pawns = EvalWeights { doubled_pawn_penalty: 0, isolated_pawn_penalty: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &pawns) - evaluate_with(&board, &no_pawns), -(2 * 10 + 2 * 15));
+
+        // a bad white bishop and a white knight on the rim against a black knight on it too
+        let board = Board::from_fen("n3k3/8/8/8/3P4/4P3/8/2B1K2N w - - 0 1").unwrap();
+        let bishop = EvalWeights { bad_bishop_penalty: 25, ..weights };
+        let no_bishop = EvalWeights { bad_bishop_penalty: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &bishop) - evaluate_with(&board, &no_bishop), -25);
+        let knights = EvalWeights { knight_on_rim_penalty: 20, ..weights };
+        let no_knights = EvalWeights { knight_on_rim_penalty: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &knights) - evaluate_with(&board, &no_knights), 0);
+        let board = Board::from_fen("4k3/8/8/8/3N4/8/8/4K2N w - - 0 1").unwrap();
+        assert_eq!(evaluate_with(&board, &knights) - evaluate_with(&board, &no_knights), -20);
+
+        // the d4 and e4 pawns hit e5 and d5, a black king on d6 hits both back
+        let board = Board::from_fen("k7/8/8/8/3PP3/8/8/4K3 w - - 0 1").unwrap();
+        let center = EvalWeights { center_control_weight: 5, ..weights };
+        let no_center = EvalWeights { center_control_weight: 0, ..weights };
+        assert_eq!(evaluate_with(&board, &center) - evaluate_with(&board, &no_center), 2 * 5);
+        let board = Board::from_fen("8/8/3k4/8/3PP3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(evaluate_with(&board, &center) - evaluate_with(&board, &no_center), 0);
+        // the same as counting the control of each center square
+        let board = Board::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8").unwrap();
+        let control = game_engine::control_count(&board);
+        let count = ["d4", "e4", "d5", "e5"].iter().map(|square| control[Address::parse(square).to_index() as usize] as i32).sum::<i32>();
+        assert_eq!(evaluate_with(&board, &center) - evaluate_with(&board, &no_center), count * 5);
+    }
+
+    // the default weights count every term the right way round
+    #[test]
+    fn evaluate_default_terms() {
+        let weights = EvalWeights::default();
+        // how much the term adds to the default score
+        let term = |fen: &str, without: EvalWeights| {
+            let board = Board::from_fen(fen).unwrap();
+            evaluate(&board) - evaluate_with(&board, &without)
+        };
+
+        assert!(term("4k3/7p/8/8/P7/6P1/8/4K3 w - - 0 1", EvalWeights { passed_pawn_bonus: 0, ..weights }) > 0);
+        assert!(term("4k3/8/8/8/8/8/7P/R3K2R w - - 0 1", EvalWeights { rook_open_file_bonus: 0, ..weights }) > 0);
+        assert!(term("3rk3/8/8/8/8/8/8/4K3 w - - 0 1", EvalWeights { king_safety_weight: 0, ..weights }) < 0);
+        assert!(term("4k3/3pp3/8/8/2P5/2P5/8/4K3 w - - 0 1", EvalWeights { doubled_pawn_penalty: 0, ..weights }) < 0);
+        assert!(term("4k3/3pp3/8/8/2P5/2P5/8/4K3 w - - 0 1", EvalWeights { isolated_pawn_penalty: 0, ..weights }) < 0);
+        assert!(term("4k3/8/8/8/3P4/4P3/8/2B1K3 w - - 0 1", EvalWeights { bad_bishop_penalty: 0, ..weights }) < 0);
+        assert!(term("4k3/8/8/8/8/8/8/4K2N w - - 0 1", EvalWeights { knight_on_rim_penalty: 0, ..weights }) < 0);
+        assert!(term("k7/8/8/8/3PP3/8/8/4K3 w - - 0 1", EvalWeights { center_control_weight: 0, ..weights }) > 0);
+    }
+
+    #[test]
+    fn evaluate_mirror_symmetry() {
+        let fens = [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8",
+            "4k3/3pp3/8/8/2P5/2P5/8/4K3 w - - 0 1",
+            "3rk3/8/8/8/8/8/7P/R3K2R b - - 0 1",
+            "8/5pk1/6p1/8/3N4/8/5PPP/6K1 w - - 0 40",
+        ];
+        let weights = EvalWeights {
+            passed_pawn_bonus: 20,
+            rook_open_file_bonus: 15,
+            king_safety_weight: 10,
+            doubled_pawn_penalty: 10,
+            isolated_pawn_penalty: 15,
+            bad_bishop_penalty: 25,
+            knight_on_rim_penalty: 20,
+            center_control_weight: 5,
+            ..EvalWeights::default()
+        };
+
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            let mirror = board.mirror_vertical();
+            assert_eq!(evaluate(&mirror), -evaluate(&board), "{}", fen);
+            assert_eq!(evaluate_with(&mirror, &weights), -evaluate_with(&board, &weights), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn evaluate_square_bonus() {
+        let center = Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let corner = Board::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+        assert!(evaluate(&center) > evaluate(&corner));
+
+        let advanced = Board::from_fen("4k3/8/4P3/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let home = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert!(evaluate(&advanced) > evaluate(&home));
+
+        // every table is read upside down for black, so mirrored positions score the same
+        for piece_type in PieceType::ALL {
+            for phase in [GamePhase::Middlegame, GamePhase::Endgame] {
+                for index in 0..CELLS_COUNT {
+                    let address = Address::from_index(index).unwrap();
+                    let mirrored = Address::new(address.col, ROW_SIZE - 1 - address.row);
+                    assert_eq!(
+                        get_square_bonus(&Piece { piece_type, color: Color::White }, address, phase),
+                        get_square_bonus(&Piece { piece_type, color: Color::Black }, mirrored, phase)
+                    );
+                }
+            }
+        }
+
+        // the king stays home in the middlegame and walks up in the endgame
+        let king = Piece { piece_type: PieceType::King, color: Color::White };
+        assert!(get_square_bonus(&king, Address::parse("g1"), GamePhase::Middlegame) > get_square_bonus(&king, Address::parse("e4"), GamePhase::Middlegame));
+        assert!(get_square_bonus(&king, Address::parse("g1"), GamePhase::Endgame) < get_square_bonus(&king, Address::parse("e4"), GamePhase::Endgame));
+    }
+}
@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use super::bitboard::{self, Bitboard};
 use super::chess_types::*;
 
 // MOVE OFFSETS
@@ -17,12 +18,12 @@ static PAWN_LONG_MARCH_OFFSET: &[(i8, i8)] = &[
     (0, 2)
 ];
 
-static PAWN_CAPTURE_OFFSETS: &[(i8, i8)] = &[
+pub(crate) static PAWN_CAPTURE_OFFSETS: &[(i8, i8)] = &[
     (-1, 1),
     ( 1, 1)
 ];
 
-static KNIGHT_MOVE_OFFSETS: &[(i8, i8)] = &[
+pub(crate) static KNIGHT_MOVE_OFFSETS: &[(i8, i8)] = &[
     (-1,  2),
     ( 1,  2),
     ( 2,  1),
@@ -33,21 +34,21 @@ static KNIGHT_MOVE_OFFSETS: &[(i8, i8)] = &[
     (-2,  1),
 ];
 
-static BISHOP_MOVE_OFFSETS: &[(i8, i8)] = &[
+pub(crate) static BISHOP_MOVE_OFFSETS: &[(i8, i8)] = &[
     (-1, -1),
     (-1,  1),
     ( 1, -1),
     ( 1,  1),
 ];
 
-static ROOK_MOVE_OFFSETS: &[(i8, i8)] = &[
+pub(crate) static ROOK_MOVE_OFFSETS: &[(i8, i8)] = &[
     (-1,  0),
     ( 0,  1),
     ( 1,  0),
     ( 0, -1),
 ];
 
-static KING_QUEEN_MOVE_OFFSETS: &[(i8, i8)] = &[
+pub(crate) static KING_QUEEN_MOVE_OFFSETS: &[(i8, i8)] = &[
     (-1,  0),
     ( 0,  1),
     ( 1,  0),
@@ -64,10 +65,73 @@ pub enum MoveError {
     NoPiece,
     WrongColorTurn(Color),
     UnreachableMove{from: Address, to: Address},
+    KingInCheck,
+    InvalidPromotion(PieceType),
+    InvalidPromotionChar(char),
+    GameOver,
 }
 
+// messages for the player like "e2 cannot reach e5" or "it is Black's turn"
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::InvalidAddress(error)        => write!(f, "invalid square: {}", error),
+            MoveError::NoPiece                      => write!(f, "there is no piece to move"),
+            // the color is the one of the piece that tried to move
+            MoveError::WrongColorTurn(color)        => write!(f, "it is {:?}'s turn", color.opposite()),
+            MoveError::UnreachableMove { from, to } => write!(f, "{} cannot reach {}", from, to),
+            MoveError::KingInCheck                  => write!(f, "the move leaves the king in check"),
+            MoveError::InvalidPromotion(piece_type) => write!(f, "a pawn cannot promote to a {}", format!("{:?}", piece_type).to_lowercase()),
+            MoveError::InvalidPromotionChar(ch)     => write!(f, "'{}' is not a piece to promote to", ch),
+            MoveError::GameOver                     => write!(f, "the game is over"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
 pub type MovesResult = Result<Vec<Address>, MoveError>;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    // no pawns at all
+    Open,
+    // half-open for the color: only pawns of the other color stand on it
+    HalfOpen(Color),
+    // pawns of both colors
+    Closed,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    Stalemate,
+    // claimed with Game::claim_draw
+    Repetition,
+    FiftyMove,
+    // drawn on the board with no claim needed
+    FivefoldRepetition,
+    SeventyFiveMove,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameStatus {
+    Ongoing,
+    Check,
+    Checkmate,
+    Draw(DrawReason),
+    // the color that resigned
+    Resignation(Color),
+    AgreedDraw,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        !matches!(self, GameStatus::Ongoing | GameStatus::Check)
+    }
+}
+
 pub fn get_piece_moves(board: &Board, address: Address) -> MovesResult {
     let piece = board.get_cell(address)
         .as_ref()
@@ -87,6 +151,288 @@ pub fn get_piece_moves(board: &Board, address: Address) -> MovesResult {
     Ok(res)
 }
 
+// Pseudo-legal moves filtered so that the mover's king is never left attacked
+pub fn get_legal_moves(board: &Board, address: Address) -> MovesResult {
+    let mut res = get_piece_moves(board, address)?;
+    let mut trial = board.clone();
+    res.retain(|to| is_king_safe_after_trial_move(&mut trial, address, *to));
+    Ok(res)
+}
+
+// legal moves of the piece with their kinds and types, a promotion is listed once per piece
+// to promote to
+pub fn get_legal_moves_detailed(board: &Board, address: Address) -> Result<Vec<Move>, MoveError> {
+    let mut res = Vec::new();
+
+    for to in get_legal_moves(board, address)? {
+        let is_capture = board.get_cell(to).is_some() || is_en_passant_move(board, address, to);
+        // a chess960 king taking its own rook is castling, not a capture
+        let move_type = if is_capture && !is_castling_move(board, address, to) { MoveType::Capture } else { MoveType::March };
+
+        if is_promotion_move(board, address, to) {
+            for promotion in PROMOTION_TYPES {
+                res.push(Move { from: address, to, kind: MoveKind::Promotion(*promotion), move_type });
+            }
+        } else {
+            res.push(Move { from: address, to, kind: get_move_kind(board, address, to), move_type });
+        }
+    }
+
+    Ok(res)
+}
+
+// every legal move of the side to move with its kind
+pub fn get_all_legal_moves_detailed(board: &Board) -> Vec<Move> {
+    bitboard::addresses(board.occupancy(board.whose_turn))
+        .flat_map(|address| get_legal_moves_detailed(board, address).unwrap_or_default())
+        .collect()
+}
+
+// kind of a move which is not a promotion
+fn get_move_kind(board: &Board, from: Address, to: Address) -> MoveKind {
+    if let Some(rook) = get_castling_rook(board, from, to) {
+        return MoveKind::Castle(if rook.col > from.col { Side::King } else { Side::Queen });
+    }
+
+    if is_en_passant_move(board, from, to) {
+        return MoveKind::EnPassant;
+    }
+
+    let is_pawn = matches!(board.get_cell(from), Some(piece) if piece.piece_type == PieceType::Pawn);
+    if is_pawn && from.row.abs_diff(to.row) == 2 {
+        return MoveKind::DoublePawnPush;
+    }
+
+    if board.get_cell(to).is_some() {
+        MoveKind::Capture
+    } else {
+        MoveKind::Normal
+    }
+}
+
+// legality of a single move: errors for a missing piece or the wrong turn,
+// false when the piece cannot get there or the king would be left attacked
+pub fn is_legal_move(board: &Board, from: Address, to: Address) -> Result<bool, MoveError> {
+    let piece = board.get_cell(from).ok_or(MoveError::NoPiece)?;
+    if piece.color != board.whose_turn {
+        return Err(MoveError::WrongColorTurn(piece.color));
+    }
+
+    Ok(is_pseudo_legal_move(board, piece, from, to) && is_king_safe_after_move(board, from, to))
+}
+
+// pieces other than pawns and castling kings move where they attack,
+// so those are answered from the attack bitboards without building a move list
+fn is_pseudo_legal_move(board: &Board, piece: Piece, from: Address, to: Address) -> bool {
+    let is_special = piece.piece_type == PieceType::Pawn || is_castling_move(board, from, to);
+
+    if is_special {
+        let mut moves = Vec::new();
+        match piece.piece_type {
+            PieceType::Pawn => get_pawn_moves(board, from, piece.color, &mut moves),
+            _               => get_castling_moves(board, from, piece.color, &mut moves),
+        }
+        return moves.contains(&to);
+    }
+
+    let attacks = get_piece_attacks(piece, from, board.occupied()) & !board.occupancy(piece.color);
+    bitboard::contains(attacks, to)
+}
+
+// whether the king of the color is attacked, false when the color has no king
+pub fn is_in_check(board: &Board, color: Color) -> bool {
+    match board.find_king(color) {
+        Some(king) => is_square_attacked(board, king, color.opposite()),
+        None => false,
+    }
+}
+
+fn is_king_safe_after_move(board: &Board, from: Address, to: Address) -> bool {
+    is_king_safe_after_trial_move(&mut board.clone(), from, to)
+}
+
+// plays the move on the trial board and takes it back, leaving the board as it was
+fn is_king_safe_after_trial_move(trial: &mut Board, from: Address, to: Address) -> bool {
+    let color = match trial.get_cell(from) {
+        Some(piece) => piece.color,
+        None => return false,
+    };
+
+    make_move_unchecked(trial, from, to);
+
+    // the king itself may be the moved piece, so look it up after the move
+    let is_safe = !is_in_check(trial, color);
+    trial.unmake_move();
+    is_safe
+}
+
+// every square attacked by the color, pawns attack their capture diagonals
+// even when those are empty, and slider rays stop at the first piece
+pub fn attacked_squares(board: &Board, by: Color) -> BoardLayer<bool> {
+    let mut attacks: Bitboard = 0;
+    let occupied = board.occupied();
+
+    for address in bitboard::addresses(board.occupancy(by)) {
+        if let Some(piece) = board.get_cell(address) {
+            attacks |= get_piece_attacks(*piece, address, occupied);
+        }
+    }
+
+    let mut res = [false; CELLS_COUNT as usize];
+    for address in bitboard::addresses(attacks) {
+        res[address.to_index() as usize] = true;
+    }
+    res
+}
+
+// white attackers minus black attackers of every square, counted the same way as
+// attacked_squares: a square both sides hit twice is 0, one only white hits once is 1
+pub fn control_count(board: &Board) -> BoardLayer<i8> {
+    let mut res = [0; CELLS_COUNT as usize];
+    let occupied = board.occupied();
+
+    for (address, piece) in board.iter_pieces() {
+        let sign = if piece.color == Color::White { 1 } else { -1 };
+        for target in bitboard::addresses(get_piece_attacks(piece, address, occupied)) {
+            res[target.to_index() as usize] += sign;
+        }
+    }
+
+    res
+}
+
+// pieces of the color attacking the square whatever stands on it, pseudo-legally:
+// pins and checks are ignored. pawns count by their capture diagonals only, never by pushes
+pub fn attackers_of(board: &Board, target: Address, by: Color) -> Vec<Address> {
+    let pieces = |piece_type: PieceType| board.bitboard(Piece { piece_type, color: by });
+    let occupied = board.occupied();
+
+    let attackers = bitboard::pawn_attacks(target, by.opposite()) & pieces(PieceType::Pawn)
+        | bitboard::knight_attacks(target) & pieces(PieceType::Knight)
+        | bitboard::king_attacks(target) & pieces(PieceType::King)
+        | bitboard::ray_attacks(target, BISHOP_MOVE_OFFSETS, occupied) & (pieces(PieceType::Bishop) | pieces(PieceType::Queen))
+        | bitboard::ray_attacks(target, ROOK_MOVE_OFFSETS, occupied) & (pieces(PieceType::Rook) | pieces(PieceType::Queen));
+
+    bitboard::addresses(attackers).collect()
+}
+
+// attackers which can actually take on the square or step onto it: the mover's king stays safe,
+// a pawn needs something to capture there or the en passant target, and own pieces are never taken
+pub fn legal_attackers_of(board: &Board, target: Address, by: Color) -> Vec<Address> {
+    if matches!(board.get_cell(target), Some(piece) if piece.color == by) {
+        return Vec::new();
+    }
+
+    attackers_of(board, target, by)
+        .into_iter()
+        .filter(|from| get_legal_moves(board, *from).is_ok_and(|moves| moves.contains(&target)))
+        .collect()
+}
+
+pub(crate) fn get_piece_attacks(piece: Piece, address: Address, occupied: Bitboard) -> Bitboard {
+    match piece.piece_type {
+        PieceType::Pawn   => bitboard::pawn_attacks(address, piece.color),
+        PieceType::Knight => bitboard::knight_attacks(address),
+        PieceType::Bishop => bitboard::ray_attacks(address, BISHOP_MOVE_OFFSETS, occupied),
+        PieceType::Rook   => bitboard::ray_attacks(address, ROOK_MOVE_OFFSETS, occupied),
+        PieceType::Queen  => bitboard::ray_attacks(address, KING_QUEEN_MOVE_OFFSETS, occupied),
+        PieceType::King   => bitboard::king_attacks(address),
+    }
+}
+
+// pieces of the color the opponent can win: attacked and undefended,
+// or attacked by something cheaper than the piece itself;
+// the king is never listed since it cannot be captured
+pub fn hanging_pieces(board: &Board, color: Color) -> Vec<Address> {
+    let enemy = color.opposite();
+    let defended = attacked_squares(board, color);
+    let occupied = board.occupied();
+    let value = |piece_type: PieceType| piece_type.get_value(GamePhase::Middlegame);
+
+    let mut cheapest_attacker = [None::<u32>; CELLS_COUNT as usize];
+    for attacker_address in bitboard::addresses(board.occupancy(enemy)) {
+        let attacker = match board.get_cell(attacker_address) {
+            Some(attacker) => *attacker,
+            None => continue,
+        };
+
+        let targets = get_piece_attacks(attacker, attacker_address, occupied) & board.occupancy(color);
+        for target in bitboard::addresses(targets) {
+            let cheapest = &mut cheapest_attacker[target.to_index() as usize];
+            *cheapest = Some(cheapest.map_or(value(attacker.piece_type), |v| v.min(value(attacker.piece_type))));
+        }
+    }
+
+    board.iter_pieces()
+        .filter(|(_, piece)| piece.color == color && piece.piece_type != PieceType::King)
+        .filter(|(address, piece)| {
+            let index = address.to_index() as usize;
+            match cheapest_attacker[index] {
+                Some(attacker_value) => !defended[index] || attacker_value < value(piece.piece_type),
+                None => false,
+            }
+        })
+        .map(|(address, _)| address)
+        .collect()
+}
+
+// absolutely pinned pieces of the color, each with the offset pointing to its own king
+pub fn pinned_pieces(board: &Board, color: Color) -> Vec<(Address, (i8, i8))> {
+    let mut res = Vec::new();
+    let king = match board.find_king(color) {
+        Some(king) => king,
+        None => return res,
+    };
+
+    for offset in KING_QUEEN_MOVE_OFFSETS {
+        let is_diagonal = offset.0 != 0 && offset.1 != 0;
+        let mut candidate = None;
+
+        for ray_address in king.ray(*offset) {
+            if let Some(piece) = board.get_cell(ray_address) {
+                if piece.color == color {
+                    if candidate.is_some() {
+                        break;
+                    }
+                    candidate = Some(ray_address);
+                } else {
+                    let is_pinner = match piece.piece_type {
+                        PieceType::Queen  => true,
+                        PieceType::Bishop => is_diagonal,
+                        PieceType::Rook   => !is_diagonal,
+                        _ => false,
+                    };
+
+                    if let (true, Some(pinned)) = (is_pinner, candidate) {
+                        res.push((pinned, (-offset.0, -offset.1)));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    res
+}
+
+// a direct lookup which is cheaper than building the whole attacked_squares map:
+// attacks are symmetric, so the square "attacks back" with every piece type
+pub fn is_square_attacked(board: &Board, address: Address, by_color: Color) -> bool {
+    let pieces = |piece_type: PieceType| board.bitboard(Piece { piece_type, color: by_color });
+    is_square_attacked_by(address, by_color, pieces, board.occupied())
+}
+
+// is_square_attacked over any piece placement, given by the attacker's bitboards
+fn is_square_attacked_by(address: Address, by_color: Color, pieces: impl Fn(PieceType) -> Bitboard, occupied: Bitboard) -> bool {
+    bitboard::pawn_attacks(address, by_color.opposite()) & pieces(PieceType::Pawn) != 0
+        || bitboard::knight_attacks(address) & pieces(PieceType::Knight) != 0
+        || bitboard::king_attacks(address) & pieces(PieceType::King) != 0
+        || bitboard::ray_attacks(address, BISHOP_MOVE_OFFSETS, occupied)
+            & (pieces(PieceType::Bishop) | pieces(PieceType::Queen)) != 0
+        || bitboard::ray_attacks(address, ROOK_MOVE_OFFSETS, occupied)
+            & (pieces(PieceType::Rook) | pieces(PieceType::Queen)) != 0
+}
+
 fn get_pawn_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
     static WHITE_PAWN_INITIAL_ROW: u8 = 1; // like e2
     static BLACK_PAWN_INITIAL_ROW: u8 = 6; // like e7
@@ -98,102 +444,324 @@ fn get_pawn_moves(board: &Board, address: Address, color: Color, out: &mut Vec<A
         if color == Color::White { offset } else { (-offset.0, -offset.1) }
     };
 
-    // long march
-    if is_initial_row {
-        let long_march = rotate_by_color(PAWN_LONG_MARCH_OFFSET[0]);
-        if let Some(move_address) = address.get_shifted(long_march) {
-            if let None = *board.get_cell(move_address) {
-                out.push(move_address);
-            }
-        }
-    }
-
     // usual march
     let normal_march = rotate_by_color(PAWN_MARCH_OFFSET[0]);
+    let mut is_march_free = false;
     if let Some(move_address) = address.get_shifted(normal_march) {
-        if let None = *board.get_cell(move_address) {
+        if board.get_cell(move_address).is_none() {
             out.push(move_address);
+            is_march_free = true;
         }
     }
 
-    // captures
-    for capture_offset in PAWN_CAPTURE_OFFSETS {
-        if let Some(move_address) = address.get_shifted(rotate_by_color(*capture_offset)) {
-            if let Some(ref piece) = *board.get_cell(move_address) {
-                if piece.color != color {
-                    out.push(move_address);
-                }
+    // long march, the skipped square has to be free as well
+    if is_initial_row && is_march_free {
+        let long_march = rotate_by_color(PAWN_LONG_MARCH_OFFSET[0]);
+        if let Some(move_address) = address.get_shifted(long_march) {
+            if board.get_cell(move_address).is_none() {
+                out.push(move_address);
             }
         }
     }
-}
 
-fn get_scalar_piece_moves(scalar_offsets: &[(i8, i8)], board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    for offset in scalar_offsets {
-        if let Some(move_address) = address.get_shifted(*offset) {
+    // captures
+    for capture_offset in PAWN_CAPTURE_OFFSETS {
+        if let Some(move_address) = address.get_shifted(rotate_by_color(*capture_offset)) {
             if let Some(ref piece) = *board.get_cell(move_address) {
                 if piece.color != color {
                     out.push(move_address);
                 }
-            } else {
+            } else if is_en_passant_target(board, move_address, color) {
                 out.push(move_address);
             }
         }
     }
 }
 
-fn get_vector_piece_moves(vector_offsets: &[(i8, i8)], board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    for offset in vector_offsets {
-        let mut addr = address.get_shifted(*offset);
-        while let Some(move_address) = addr {
-            if let Some(ref piece) = *board.get_cell(move_address) {
-                if piece.color != color {
-                    out.push(move_address);
-                }
-                break;
-            } else {
-                out.push(move_address);
-            }
+fn is_en_passant_target(board: &Board, address: Address, color: Color) -> bool {
+    static WHITE_EN_PASSANT_ROW: u8 = 5; // like e6
+    static BLACK_EN_PASSANT_ROW: u8 = 2; // like e3
 
-            addr = move_address.get_shifted(*offset);
-        }
+    let capture_row = if color == Color::White { WHITE_EN_PASSANT_ROW } else { BLACK_EN_PASSANT_ROW };
+    board.en_passant_target == Some(address) && address.row == capture_row
+}
+
+fn is_en_passant_move(board: &Board, from: Address, to: Address) -> bool {
+    match board.get_cell(from) {
+        Some(piece) => piece.piece_type == PieceType::Pawn
+            && from.col != to.col
+            && board.get_cell(to).is_none()
+            && board.en_passant_target == Some(to),
+        None => false,
     }
 }
 
+// attacked squares minus the ones taken by own pieces
+fn push_attack_moves(attacks: Bitboard, board: &Board, color: Color, out: &mut Vec<Address>) {
+    out.extend(bitboard::addresses(attacks & !board.occupancy(color)));
+}
+
 fn get_knight_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    get_scalar_piece_moves(KNIGHT_MOVE_OFFSETS, board, address, color, out);
+    push_attack_moves(bitboard::knight_attacks(address), board, color, out);
 }
 
 fn get_bishop_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    get_vector_piece_moves(BISHOP_MOVE_OFFSETS, board, address, color, out);
+    push_attack_moves(bitboard::ray_attacks(address, BISHOP_MOVE_OFFSETS, board.occupied()), board, color, out);
 }
 
 fn get_rook_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    get_vector_piece_moves(ROOK_MOVE_OFFSETS, board, address, color, out);
+    push_attack_moves(bitboard::ray_attacks(address, ROOK_MOVE_OFFSETS, board.occupied()), board, color, out);
 }
 
 fn get_queen_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    get_vector_piece_moves(KING_QUEEN_MOVE_OFFSETS, board, address, color, out);
+    push_attack_moves(bitboard::ray_attacks(address, KING_QUEEN_MOVE_OFFSETS, board.occupied()), board, color, out);
 }
 
 fn get_king_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
-    get_scalar_piece_moves(KING_QUEEN_MOVE_OFFSETS, board, address, color, out);
+    push_attack_moves(bitboard::king_attacks(address), board, color, out);
+    get_castling_moves(board, address, color, out);
+}
+
+// the king goes to the g or c file and the rook next to it on the inner side;
+// every square both of them cross has to be free and the king's way safe.
+// regular boards write it as the king's two-square step, chess960 ones as the king taking its rook
+fn get_castling_moves(board: &Board, address: Address, color: Color, out: &mut Vec<Address>) {
+    let (home_row, king_side, queen_side) = if color == Color::White {
+        (0, WHITE_KING_SIDE, WHITE_QUEEN_SIDE)
+    } else {
+        (7, BLACK_KING_SIDE, BLACK_QUEEN_SIDE)
+    };
+
+    if address.row != home_row || (!board.chess960 && address.col != 4) {
+        return;
+    }
+
+    let enemy = color.opposite();
+    for right in [king_side, queen_side] {
+        if !board.castling_rights[right] {
+            continue;
+        }
+
+        let rook_from = Address::new(board.castling_rook_cols[right % 2], home_row);
+        let has_rook = matches!(
+            board.get_cell(rook_from),
+            Some(piece) if piece.piece_type == PieceType::Rook && piece.color == color
+        );
+        let is_rook_on_its_side = (right == king_side) == (rook_from.col > address.col);
+        if !has_rook || !is_rook_on_its_side {
+            continue;
+        }
+
+        let (king_to, rook_to) = Board::get_castled_squares(address, rook_from);
+        let span = |a: u8, b: u8| a.min(b)..=a.max(b);
+
+        let is_path_empty = span(address.col, king_to.col).chain(span(rook_from.col, rook_to.col))
+            .filter(|c| *c != address.col && *c != rook_from.col)
+            .all(|c| board.get_cell(Address::new(c, home_row)).is_none());
+        let is_path_safe = span(address.col, king_to.col)
+            .all(|c| !is_square_attacked(board, Address::new(c, home_row), enemy));
+
+        if is_path_empty && is_path_safe {
+            out.push(if board.chess960 { rook_from } else { king_to });
+        }
+    }
+}
+
+// whether the color may castle to that side right now as far as the board goes: the right is
+// still there, king and rook stand on their home squares with nothing between them, and the
+// king is not in check and crosses no attacked square. whose turn it is is not looked at
+pub fn can_castle(board: &Board, color: Color, side: Side) -> bool {
+    let king = match board.find_king(color) {
+        Some(king) => king,
+        None => return false,
+    };
+
+    let mut moves = Vec::new();
+    get_castling_moves(board, king, color, &mut moves);
+
+    moves.into_iter()
+        .filter_map(|to| get_castling_rook(board, king, to))
+        .any(|rook| (rook.col > king.col) == (side == Side::King))
+}
+
+// whether the move attacks the enemy king directly or by uncovering a slider,
+// en passant and castling included. the position after the move is only
+// pictured in bitboards, so it is cheap enough to ask for every generated move
+pub fn move_gives_check(board: &Board, mv: Move) -> bool {
+    let piece = match board.get_cell(mv.from) {
+        Some(piece) => *piece,
+        None => return false,
+    };
+    let enemy_king = match board.find_king(piece.color.opposite()) {
+        Some(king) => king,
+        None => return false,
+    };
+
+    // squares emptied by the move and the pieces of the mover placed by it
+    let mut removed = bitboard::of(mv.from);
+    let mut placed = Vec::with_capacity(2);
+
+    if let Some(rook_from) = get_castling_rook(board, mv.from, mv.to) {
+        let (king_to, rook_to) = Board::get_castled_squares(mv.from, rook_from);
+        removed |= bitboard::of(rook_from);
+        placed.push((king_to, PieceType::King));
+        placed.push((rook_to, PieceType::Rook));
+    } else {
+        if is_en_passant_move(board, mv.from, mv.to) {
+            removed |= bitboard::of(Address::new(mv.to.col, mv.from.row));
+        }
+
+        let piece_type = if is_promotion_move(board, mv.from, mv.to) {
+            mv.promotion().unwrap_or(PieceType::Queen)
+        } else {
+            piece.piece_type
+        };
+        placed.push((mv.to, piece_type));
+    }
+
+    let placed_bits = |piece_type: PieceType| {
+        placed.iter()
+            .filter(|(_, placed_type)| *placed_type == piece_type)
+            .fold(0, |bits, (address, _)| bits | bitboard::of(*address))
+    };
+
+    let pieces = |piece_type: PieceType| {
+        board.bitboard(Piece { piece_type, color: piece.color }) & !removed | placed_bits(piece_type)
+    };
+    let occupied = board.occupied() & !removed | placed.iter().fold(0, |bits, (address, _)| bits | bitboard::of(*address));
+
+    is_square_attacked_by(enemy_king, piece.color, pieces, occupied)
 }
 
-pub fn make_move(board: &mut Board, from: Address, to: Address) -> Result<(), MoveError> {
-    if let Some(piece) = board.get_cell(from) {
-        if piece.color != board.whose_turn {
-            return Err(MoveError::NoPiece);
+// home square of the rook the king castles with, None for any other move
+fn get_castling_rook(board: &Board, from: Address, to: Address) -> Option<Address> {
+    let king = match board.get_cell(from) {
+        Some(piece) if piece.piece_type == PieceType::King => *piece,
+        _ => return None,
+    };
+
+    if board.chess960 {
+        match board.get_cell(to) {
+            Some(piece) if piece.piece_type == PieceType::Rook && piece.color == king.color => Some(to),
+            _ => None,
         }
+    } else if from.col.abs_diff(to.col) == 2 {
+        let side = if to.col > from.col { 0 } else { 1 };
+        Some(Address::new(board.castling_rook_cols[side], from.row))
+    } else {
+        None
+    }
+}
+
+fn is_castling_move(board: &Board, from: Address, to: Address) -> bool {
+    get_castling_rook(board, from, to).is_some()
+}
+
+// a move without a promotion piece promotes to a queen
+pub fn make_move(board: &mut Board, mv: Move) -> Result<(), MoveError> {
+    make_move_promoting(board, mv.from, mv.to, mv.promotion().unwrap_or(PieceType::Queen))
+}
+
+pub fn make_move_coords(board: &mut Board, from: Address, to: Address) -> Result<(), MoveError> {
+    make_move_promoting(board, from, to, PieceType::Queen)
+}
+
+// same as make_move, but a pawn reaching the last rank turns into `promotion`
+pub fn make_move_promoting(board: &mut Board, from: Address, to: Address, promotion: PieceType) -> Result<(), MoveError> {
+    let piece = board.get_cell(from).ok_or(MoveError::NoPiece)?;
+    if piece.color != board.whose_turn {
+        return Err(MoveError::WrongColorTurn(piece.color));
+    }
+
+    if !is_pseudo_legal_move(board, piece, from, to) {
+        return Err(MoveError::UnreachableMove { from, to });
+    }
+
+    if !is_king_safe_after_move(board, from, to) {
+        return Err(MoveError::KingInCheck);
+    }
+
+    if is_promotion_move(board, from, to)
+    && matches!(promotion, PieceType::Pawn | PieceType::King) {
+        return Err(MoveError::InvalidPromotion(promotion));
     }
-    let possible_moves = get_piece_moves(&board, from)?;
 
-    if possible_moves.contains(&to) {
-        board.move_piece(from, to);
-        board.flip_player();
-        Ok(())
+    board.redo_history.clear();
+    make_move_unchecked_promoting(board, from, to, promotion);
+    Ok(())
+}
+
+// plays a move already known to be legal, skipping all validation;
+// take it back with Board::unmake_move which restores from the history record
+pub(crate) fn make_move_unchecked(board: &mut Board, from: Address, to: Address) {
+    make_move_unchecked_promoting(board, from, to, PieceType::Queen);
+}
+
+pub(crate) fn make_move_unchecked_promoting(board: &mut Board, from: Address, to: Address, promotion: PieceType) {
+    apply_move(board, from, to, promotion);
+    board.flip_player();
+}
+
+pub(crate) fn is_promotion_move(board: &Board, from: Address, to: Address) -> bool {
+    match board.get_cell(from) {
+        Some(piece) => piece.piece_type == PieceType::Pawn && (to.row == 0 || to.row == ROW_SIZE - 1),
+        None => false,
+    }
+}
+
+// performs the move with all of its side effects and records it, without any validation
+pub(crate) fn apply_move(board: &mut Board, from: Address, to: Address, promotion: PieceType) {
+    let piece = match board.get_cell(from) {
+        Some(piece) => *piece,
+        None => return,
+    };
+
+    let is_promotion = is_promotion_move(board, from, to);
+    let castling_rook = get_castling_rook(board, from, to);
+    let captured = if is_en_passant_move(board, from, to) {
+        *board.get_cell(Address::new(to.col, from.row))
+    } else if castling_rook.is_some() {
+        None
+    } else {
+        *board.get_cell(to)
+    };
+
+    board.history.push(MoveRecord {
+        from,
+        to,
+        piece,
+        captured,
+        promotion: if is_promotion { Some(promotion) } else { None },
+        castling_rights: board.castling_rights,
+        en_passant_target: board.en_passant_target,
+        halfmove_clock: board.halfmove_clock,
+        castling_rook,
+        #[cfg(debug_assertions)]
+        before: Some(PositionSnapshot::of(board)),
+    });
+
+    if is_en_passant_move(board, from, to) {
+        // the captured pawn stands next to the capturing one
+        board.kill_piece(Address::new(to.col, from.row));
+    }
+
+    match castling_rook {
+        Some(rook_from) => board.castle(from, rook_from),
+        None => board.move_piece(from, to),
+    }
+
+    if is_promotion {
+        board.set_cell(to, Some(Piece { piece_type: promotion, color: piece.color }));
+    }
+
+    if piece.piece_type == PieceType::Pawn || captured.is_some() {
+        board.halfmove_clock = 0;
     } else {
-        Err(MoveError::UnreachableMove { from, to })
+        board.halfmove_clock += 1;
+    }
+
+    if piece.color == Color::Black {
+        board.fullmove_number += 1;
     }
 }
 
@@ -203,7 +771,7 @@ pub fn make_moves(board: &mut Board, moves: Vec<(&str, &str)>) -> Result<(), Mov
             MoveError::InvalidAddress(parse_error)
         };
 
-        make_move(board,
+        make_move_coords(board,
             Address::from_str(m.0).map_err(wrap_error)?,
             Address::from_str(m.1).map_err(wrap_error)?
         )?
@@ -212,18 +780,2239 @@ pub fn make_moves(board: &mut Board, moves: Vec<(&str, &str)>) -> Result<(), Mov
     Ok(())
 }
 
-#[cfg(test)]
-mod test {
-    use std::str::FromStr;
+// plays the moves one by one and records the status after each of them, for scrubbing
+// through a game. on an illegal move the statuses so far come back along with the error,
+// the moves before it stay played
+pub fn play_sequence(board: &mut Board, moves: &[Move]) -> Result<Vec<GameStatus>, (Vec<GameStatus>, MoveError)> {
+    let mut res = Vec::with_capacity(moves.len());
 
-    use super::*;
+    for mv in moves {
+        if let Err(error) = make_move(board, *mv) {
+            return Err((res, error));
+        }
+        res.push(game_status(board));
+    }
 
-    #[test]
-    fn board_moves() {
-        let board = Board::new();
-        let res = get_piece_moves(&board, Address::from_str("e2").unwrap());
-        assert_eq!(res.is_ok(), true);
+    Ok(res)
+}
 
-        println!("{:?}", res.unwrap());
+// every problem of a move list at once: the moves are tried in order on a copy of the board,
+// a bad one is reported with its index and skipped and the rest are played on.
+// the board itself is left as it is
+pub fn validate_moves(board: &Board, moves: Vec<(&str, &str)>) -> Vec<(usize, MoveError)> {
+    let mut trial = board.clone();
+
+    moves.into_iter()
+        .enumerate()
+        .filter_map(|(index, m)| make_moves(&mut trial, vec![m]).err().map(|error| (index, error)))
+        .collect()
+}
+
+// long algebraic move like "e2e4" or "e7e8q"
+pub fn parse_uci_move(s: &str) -> Result<(Address, Address, Option<PieceType>), MoveError> {
+    if !s.is_ascii() || (s.len() != 4 && s.len() != 5) {
+        return Err(MoveError::InvalidAddress(ParseAddressError::WrongLength(s.chars().count())));
+    }
+
+    let from = Address::from_str(&s[0..2]).map_err(MoveError::InvalidAddress)?;
+    let to = Address::from_str(&s[2..4]).map_err(MoveError::InvalidAddress)?;
+    let promotion = match s[4..].chars().next() {
+        Some(ch) => match PieceType::from_char(ch) {
+            Some(PieceType::Pawn) => return Err(MoveError::InvalidPromotion(PieceType::Pawn)),
+            Some(PieceType::King) => return Err(MoveError::InvalidPromotion(PieceType::King)),
+            Some(piece_type)      => Some(piece_type),
+            None                  => return Err(MoveError::InvalidPromotionChar(ch)),
+        },
+        None => None,
+    };
+
+    Ok((from, to, promotion))
+}
+
+pub fn format_uci_move(from: Address, to: Address, promotion: Option<PieceType>) -> String {
+    let mut res = format!("{}{}", from, to);
+    if let Some(piece_type) = promotion {
+        res.push(piece_type.to_char().to_ascii_lowercase());
+    }
+    res
+}
+
+pub(crate) fn has_legal_moves(board: &Board, color: Color) -> bool {
+    board.iter_pieces()
+        .filter(|(_, piece)| piece.color == color)
+        .any(|(addr, _)| matches!(get_legal_moves(board, addr), Ok(moves) if !moves.is_empty()))
+}
+
+// every legal (from, to) pair of the given color
+pub fn get_all_legal_moves(board: &Board, color: Color) -> Vec<(Address, Address)> {
+    let mut res = Vec::new();
+
+    for (addr, piece) in board.iter_pieces() {
+        if piece.color != color {
+            continue;
+        }
+
+        if let Ok(moves) = get_legal_moves(board, addr) {
+            res.extend(moves.into_iter().map(|to| (addr, to)));
+        }
+    }
+
+    res
+}
+
+// number of legal moves of every piece of the color, pieces without moves included;
+// a promotion counts as one move
+pub fn mobility_by_piece(board: &Board, color: Color) -> Vec<(Address, u32)> {
+    bitboard::addresses(board.occupancy(color))
+        .map(|addr| (addr, get_legal_moves(board, addr).map_or(0, |moves| moves.len() as u32)))
+        .collect()
+}
+
+pub fn mobility(board: &Board, color: Color) -> u32 {
+    mobility_by_piece(board, color).iter().map(|(_, count)| count).sum()
+}
+
+// like mobility but without checking the king's safety, which is what makes legal moves expensive
+pub fn pseudo_mobility(board: &Board, color: Color) -> u32 {
+    bitboard::addresses(board.occupancy(color))
+        .map(|addr| get_piece_moves(board, addr).map_or(0, |moves| moves.len() as u32))
+        .sum()
+}
+
+// legal moves of the color that take an enemy piece, en passant and capturing promotions included
+pub fn get_capture_moves(board: &Board, color: Color) -> Vec<(Address, Address)> {
+    let mut res = Vec::new();
+    let mut trial = board.clone();
+    let enemies = board.occupancy(color.opposite());
+
+    for (addr, piece) in board.iter_pieces() {
+        if piece.color != color {
+            continue;
+        }
+
+        for to in get_piece_moves(board, addr).unwrap_or_default() {
+            let is_capture = bitboard::contains(enemies, to) || is_en_passant_move(board, addr, to);
+            if is_capture && is_king_safe_after_trial_move(&mut trial, addr, to) {
+                res.push((addr, to));
+            }
+        }
+    }
+
+    res
+}
+
+// the color is in check with no legal move, whether or not it is its turn
+pub fn is_checkmate(board: &Board, color: Color) -> bool {
+    is_in_check(board, color) && !has_legal_moves(board, color)
+}
+
+// the color is not in check but has no legal move, whether or not it is its turn
+pub fn is_stalemate(board: &Board, color: Color) -> bool {
+    !is_in_check(board, color) && !has_legal_moves(board, color)
+}
+
+// status of the side to move judged by the position alone,
+// history-dependent draws like repetition are detected by Game
+// after fifty moves without a capture or a pawn move either side may claim a draw,
+// see Game::draw_claim_available, after seventy-five the game is drawn right away
+pub const FIFTY_MOVE_RULE_PLIES: u32 = 100;
+pub const SEVENTY_FIVE_MOVE_RULE_PLIES: u32 = 150;
+
+// the status of the board on its own: claimable draws and repetitions need a Game
+pub fn game_status(board: &Board) -> GameStatus {
+    let color = board.whose_turn;
+    let is_check = is_in_check(board, color);
+
+    if has_legal_moves(board, color) {
+        if board.halfmove_clock >= SEVENTY_FIVE_MOVE_RULE_PLIES {
+            GameStatus::Draw(DrawReason::SeventyFiveMove)
+        } else if is_check {
+            GameStatus::Check
+        } else {
+            GameStatus::Ongoing
+        }
+    } else if is_check {
+        GameStatus::Checkmate
+    } else {
+        GameStatus::Draw(DrawReason::Stalemate)
+    }
+}
+
+// PGN result of a status: "1-0", "0-1", "1/2-1/2", or "*" while the game goes on.
+// on checkmate the side to move is the one mated, so it is the side that lost
+pub fn result_string(status: GameStatus, side_to_move: Color) -> &'static str {
+    let loss = |loser: Color| if loser == Color::White { "0-1" } else { "1-0" };
+
+    match status {
+        GameStatus::Checkmate                        => loss(side_to_move),
+        GameStatus::Resignation(color)               => loss(color),
+        GameStatus::Draw(_) | GameStatus::AgreedDraw => "1/2-1/2",
+        GameStatus::Ongoing | GameStatus::Check      => "*",
+    }
+}
+
+// phase judged by non-pawn material and development:
+// opening while most pieces are home early in the game,
+// endgame once the material is thin or the queens are gone with little left
+pub fn detect_phase(board: &Board) -> GamePhase {
+    static OPENING_MAX_FULLMOVES: u32 = 10;
+    static OPENING_MIN_HOME_PIECES: usize = 12;
+    static ENDGAME_MATERIAL: u32 = 2600;
+    static ENDGAME_MATERIAL_NO_QUEENS: u32 = 3300;
+    // back rank of the start position from the a file, the same for both colors
+    static HOME_RANK: [PieceType; ROW_SIZE as usize] = [
+        PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen,
+        PieceType::King, PieceType::Bishop, PieceType::Knight, PieceType::Rook,
+    ];
+
+    let mut material = 0;
+    let mut has_queens = false;
+
+    for (_, piece) in board.iter_pieces() {
+        match piece.piece_type {
+            PieceType::Pawn | PieceType::King => continue,
+            PieceType::Queen => has_queens = true,
+            _ => {},
+        }
+        material += piece.piece_type.get_value(GamePhase::Middlegame);
+    }
+
+    if material <= ENDGAME_MATERIAL || (!has_queens && material <= ENDGAME_MATERIAL_NO_QUEENS) {
+        return GamePhase::Endgame;
+    }
+
+    let home_pieces = [(0, Color::White), (ROW_SIZE - 1, Color::Black)].iter()
+        .flat_map(|&(row, color)| {
+            (0..ROW_SIZE).zip(HOME_RANK).map(move |(col, piece_type)| (Address::new(col, row), Piece { piece_type, color }))
+        })
+        .filter(|&(addr, piece)| *board.get_cell(addr) == Some(piece))
+        .count();
+
+    if board.fullmove_number <= OPENING_MAX_FULLMOVES && home_pieces >= OPENING_MIN_HOME_PIECES {
+        GamePhase::Opening
+    } else {
+        GamePhase::Middlegame
+    }
+}
+
+// a pawn no enemy pawn can stop: none ahead of it on its own or the adjacent files,
+// false for an empty square or any other piece
+pub fn is_passed_pawn(board: &Board, address: Address) -> bool {
+    let pawn = match board.get_cell(address) {
+        Some(piece) if piece.piece_type == PieceType::Pawn => *piece,
+        _ => return false,
+    };
+
+    let enemy_pawn = Some(Piece { piece_type: PieceType::Pawn, color: pawn.color.opposite() });
+    let forward = if pawn.color == Color::White { 1 } else { -1 };
+    let files = address.col.saturating_sub(1)..=(address.col + 1).min(ROW_SIZE - 1);
+
+    files
+        .flat_map(|col| Address::new(col, address.row).ray((0, forward)))
+        .all(|addr| *board.get_cell(addr) != enemy_pawn)
+}
+
+// files without a pawn of either color, from a to h as column indices
+pub fn open_files(board: &Board) -> Vec<u8> {
+    (0..ROW_SIZE)
+        .filter(|&col| file_status(board, col) == FileStatus::Open)
+        .collect()
+}
+
+// pawns on the file given as a column index from 0 for a to 7 for h
+pub fn file_status(board: &Board, file: u8) -> FileStatus {
+    let mut has_pawns = [false; 2];
+
+    for row in 0..ROW_SIZE {
+        if let Some(piece) = board.get_cell(Address::new(file, row)) {
+            if piece.piece_type == PieceType::Pawn {
+                has_pawns[piece.color as usize] = true;
+            }
+        }
+    }
+
+    match has_pawns {
+        [false, false] => FileStatus::Open,
+        [false, true]  => FileStatus::HalfOpen(Color::White),
+        [true, false]  => FileStatus::HalfOpen(Color::Black),
+        [true, true]   => FileStatus::Closed,
+    }
+}
+
+// number of the color's pawns on each file from a to h
+fn get_pawns_per_file(board: &Board, color: Color) -> [u32; ROW_SIZE as usize] {
+    let mut res = [0; ROW_SIZE as usize];
+    for addr in bitboard::addresses(board.bitboard(Piece { piece_type: PieceType::Pawn, color })) {
+        res[addr.col as usize] += 1;
+    }
+    res
+}
+
+// pawns sharing their file with another pawn of the color, so a doubled pair counts as 2
+pub fn doubled_pawns(board: &Board, color: Color) -> u32 {
+    get_pawns_per_file(board, color).iter()
+        .filter(|&&count| count > 1)
+        .sum()
+}
+
+// pawns without a pawn of the color on either adjacent file
+pub fn isolated_pawns(board: &Board, color: Color) -> u32 {
+    let files = get_pawns_per_file(board, color);
+
+    (0..files.len())
+        .filter(|&col| {
+            let left = col.checked_sub(1).map_or(0, |col| files[col]);
+            let right = files.get(col + 1).copied().unwrap_or(0);
+            left == 0 && right == 0
+        })
+        .map(|col| files[col])
+        .sum()
+}
+
+// a bishop hemmed in by its own pawns: more of them stand on squares of the bishop's color
+// than on the other color. false when there is no bishop on the square
+pub fn is_bad_bishop(board: &Board, address: Address) -> bool {
+    let color = match board.get_cell(address) {
+        Some(piece) if piece.piece_type == PieceType::Bishop => piece.color,
+        _ => return false,
+    };
+
+    let pawns = board.bitboard(Piece { piece_type: PieceType::Pawn, color });
+    let (same, other) = match address.get_color() {
+        Color::White => (bitboard::LIGHT_SQUARES, bitboard::DARK_SQUARES),
+        Color::Black => (bitboard::DARK_SQUARES, bitboard::LIGHT_SQUARES),
+    };
+
+    (pawns & same).count_ones() > (pawns & other).count_ones()
+}
+
+// a knight on the a or h file or on the first or last rank, where it reaches half
+// the squares or fewer. false when there is no knight on the square
+pub fn is_knight_on_rim(board: &Board, address: Address) -> bool {
+    let is_knight = matches!(board.get_cell(address), Some(piece) if piece.piece_type == PieceType::Knight);
+    let edges = [0, ROW_SIZE - 1];
+
+    is_knight && (edges.contains(&address.col) || edges.contains(&address.row))
+}
+
+// checks that the FEN both parses and describes a position a game can reach the usual
+// way: one king per side, no pawns on the first or last rank and an en passant target
+// right behind an enemy pawn that has just made a double push
+pub fn validate_fen(fen: &str) -> Result<(), FenError> {
+    let board = Board::from_fen(fen)?;
+
+    for color in [Color::White, Color::Black] {
+        let count = board.bitboard(Piece { piece_type: PieceType::King, color }).count_ones();
+        if count != 1 {
+            return Err(FenError::KingCount { color, count });
+        }
+    }
+
+    let back_ranks = [0, ROW_SIZE - 1];
+    for (address, piece) in board.iter_pieces() {
+        if piece.piece_type == PieceType::Pawn && back_ranks.contains(&address.row) {
+            return Err(FenError::PawnOnBackRank(address));
+        }
+    }
+
+    if let Some(target) = board.en_passant_target {
+        // the pushed pawn belongs to the side that is not to move
+        let pushed = board.whose_turn.opposite();
+        let (target_row, forward) = if pushed == Color::White { (2, 1) } else { (ROW_SIZE - 3, -1) };
+
+        let is_consistent = target.row == target_row
+            && board.get_cell(target).is_none()
+            && target.get_shifted((0, -forward)).is_some_and(|from| board.get_cell(from).is_none())
+            && target.get_shifted((0, forward)).is_some_and(|pawn| {
+                *board.get_cell(pawn) == Some(Piece { piece_type: PieceType::Pawn, color: pushed })
+            });
+
+        if !is_consistent {
+            return Err(FenError::EnPassantMismatch(target));
+        }
+    }
+
+    Ok(())
+}
+
+static PROMOTION_TYPES: &[PieceType] = &[PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+// every legal move with promotions expanded into each possible piece
+pub(crate) fn get_all_legal_moves_promoting(board: &Board) -> Vec<(Address, Address, PieceType)> {
+    let mut res = Vec::new();
+
+    for (from, to) in get_all_legal_moves(board, board.whose_turn) {
+        if is_promotion_move(board, from, to) {
+            res.extend(PROMOTION_TYPES.iter().map(|p| (from, to, *p)));
+        } else {
+            res.push((from, to, PieceType::Queen));
+        }
+    }
+
+    res
+}
+
+// number of leaf nodes of the legal move tree at the given depth
+pub fn perft(board: &mut Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = get_all_legal_moves_promoting(board);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for (from, to, promotion) in moves {
+        make_move_unchecked_promoting(board, from, to, promotion);
+        nodes += perft(board, depth - 1);
+        board.unmake_move();
+    }
+
+    nodes
+}
+
+// leaf counts of perft_detailed, laid out like the published perft tables:
+// captures include en passant and capturing promotions, checks include checkmates
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passant: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub checkmates: u64,
+}
+
+impl std::ops::AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+// perft that also sorts the moves leading to the leaves by what they do,
+// so a wrong node count can be traced to a move category
+pub fn perft_detailed(board: &mut Board, depth: u32) -> PerftStats {
+    let mut res = PerftStats::default();
+    if depth == 0 {
+        res.nodes = 1;
+        return res;
+    }
+
+    for mv in get_all_legal_moves_detailed(board) {
+        let promotion = mv.promotion().unwrap_or(PieceType::Queen);
+
+        if depth > 1 {
+            make_move_unchecked_promoting(board, mv.from, mv.to, promotion);
+            res += perft_detailed(board, depth - 1);
+            board.unmake_move();
+            continue;
+        }
+
+        res.nodes += 1;
+        match mv.kind {
+            MoveKind::EnPassant    => res.en_passant += 1,
+            MoveKind::Castle(_)    => res.castles += 1,
+            MoveKind::Promotion(_) => res.promotions += 1,
+            _ => {},
+        }
+        if mv.move_type == MoveType::Capture {
+            res.captures += 1;
+        }
+
+        if move_gives_check(board, mv) {
+            res.checks += 1;
+
+            make_move_unchecked_promoting(board, mv.from, mv.to, promotion);
+            if !has_legal_moves(board, board.whose_turn) {
+                res.checkmates += 1;
+            }
+            board.unmake_move();
+        }
+    }
+
+    res
+}
+
+// perft split by root moves, written like "e2e4" or "a7a8n"
+pub fn perft_divide(board: &mut Board, depth: u32) -> Vec<(String, u64)> {
+    let mut res = Vec::new();
+    if depth == 0 {
+        return res;
+    }
+
+    for (from, to, promotion) in get_all_legal_moves_promoting(board) {
+        let promotion_suffix = is_promotion_move(board, from, to).then_some(promotion);
+        let name = format_uci_move(from, to, promotion_suffix);
+
+        make_move_unchecked_promoting(board, from, to, promotion);
+        res.push((name, perft(board, depth - 1)));
+        board.unmake_move();
+    }
+
+    res
+}
+
+// uniformly picked legal move of the side to move, promotions to each piece count separately
+#[cfg(feature = "rand")]
+pub fn random_move(board: &Board, rng: &mut impl rand::Rng) -> Option<Move> {
+    use rand::seq::SliceRandom;
+
+    get_all_legal_moves_detailed(board).choose(rng).copied()
+}
+
+// plays random moves on the board until the game is over and returns them,
+// the fifty-move rule keeps every game finite
+#[cfg(feature = "rand")]
+pub fn play_random_game(board: &mut Board, rng: &mut impl rand::Rng) -> Vec<Move> {
+    let mut res = Vec::new();
+
+    while !game_status(board).is_over() {
+        let mv = match random_move(board, rng) {
+            Some(mv) => mv,
+            None => break,
+        };
+
+        make_move_unchecked_promoting(board, mv.from, mv.to, mv.promotion().unwrap_or(PieceType::Queen));
+        res.push(mv);
+    }
+
+    res
+}
+
+// SAN of a move computed on the board before the move is played
+pub fn move_to_san(board: &Board, from: Address, to: Address) -> String {
+    move_to_san_promoting(board, from, to, PieceType::Queen)
+}
+
+pub fn move_to_san_promoting(board: &Board, from: Address, to: Address, promotion: PieceType) -> String {
+    let piece = match board.get_cell(from) {
+        Some(piece) => *piece,
+        None => return String::new(),
+    };
+
+    let mut res = String::new();
+
+    if is_castling_move(board, from, to) {
+        res += if to.col > from.col { "O-O" } else { "O-O-O" };
+    } else {
+        let is_capture = board.get_cell(to).is_some() || is_en_passant_move(board, from, to);
+
+        if piece.piece_type == PieceType::Pawn {
+            if is_capture {
+                res.push(Address::get_col_name(from.col));
+            }
+        } else {
+            res.push(piece.piece_type.to_char());
+            res += &get_san_disambiguation(board, piece, from, to);
+        }
+
+        if is_capture {
+            res.push('x');
+        }
+
+        res += &to.to_string();
+
+        if is_promotion_move(board, from, to) {
+            res.push('=');
+            res.push(promotion.to_char());
+        }
+    }
+
+    // only a checking move needs the position after it, to tell check from mate
+    if move_gives_check(board, Move::new(from, to, Some(promotion))) {
+        let mut after = board.clone();
+        make_move_unchecked_promoting(&mut after, from, to, promotion);
+        res.push(if has_legal_moves(&after, piece.color.opposite()) { '+' } else { '#' });
+    }
+
+    res
+}
+
+// finds the legal move of the side to move matching the SAN token,
+// check marks and annotation glyphs like "!?" are ignored
+pub fn san_to_move(board: &Board, san: &str) -> Option<(Address, Address, Option<PieceType>)> {
+    let strip = |s: &str| -> String {
+        s.trim_end_matches(['+', '#', '!', '?']).replace('0', "O")
+    };
+    let wanted = strip(san);
+
+    for (from, to) in get_all_legal_moves(board, board.whose_turn) {
+        if is_promotion_move(board, from, to) {
+            for promotion in PROMOTION_TYPES {
+                if strip(&move_to_san_promoting(board, from, to, *promotion)) == wanted {
+                    return Some((from, to, Some(*promotion)));
+                }
+            }
+        } else if strip(&move_to_san(board, from, to)) == wanted {
+            return Some((from, to, None));
+        }
+    }
+
+    None
+}
+
+// the other pieces of the same kind legally reaching the square decide what names the mover:
+// its file when no rival shares it, otherwise its rank when no rival shares that, otherwise both
+fn get_san_disambiguation(board: &Board, piece: Piece, from: Address, to: Address) -> String {
+    let rivals = legal_attackers_of(board, to, piece.color).into_iter()
+        .filter(|&addr| addr != from && *board.get_cell(addr) == Some(piece))
+        .collect::<Vec<_>>();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|a| a.col != from.col) {
+        Address::get_col_name(from.col).to_string()
+    } else if rivals.iter().all(|a| a.row != from.row) {
+        Address::get_row_name(from.row).to_string()
+    } else {
+        from.to_string()
+    }
+}
+
+// a grid cell as it is drawn, counted row by row from the top left corner, to the square
+// shown there: a8 comes first normally and h1 when the board is flipped. None past the grid.
+// the json handlers below take board indices, so the frontend converts clicks with this
+pub fn display_index_to_address(board: &Board, index: usize) -> Option<Address> {
+    if index >= CELLS_COUNT as usize {
+        return None;
+    }
+
+    let row = (index / ROW_SIZE as usize) as u8;
+    let col = (index % ROW_SIZE as usize) as u8;
+
+    if board.is_flipped() {
+        Some(Address::new(ROW_SIZE - 1 - col, row))
+    } else {
+        Some(Address::new(col, ROW_SIZE - 1 - row))
+    }
+}
+
+pub fn address_to_display_index(board: &Board, address: Address) -> usize {
+    let (col, row) = if board.is_flipped() {
+        (ROW_SIZE - 1 - address.col, address.row)
+    } else {
+        (address.col, ROW_SIZE - 1 - address.row)
+    };
+
+    row as usize * ROW_SIZE as usize + col as usize
+}
+
+// legal destinations of the clicked square as board indices for the webview,
+// like [{"address":28}], empty when there is nothing to move there
+#[cfg(feature = "json")]
+pub fn legal_moves_json(board: &Board, address: Address) -> String {
+    #[derive(serde::Serialize)]
+    struct Destination {
+        address: u8,
+    }
+
+    let destinations = match board.get_cell(address) {
+        Some(piece) if piece.color == board.whose_turn => get_legal_moves(board, address).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let res = destinations.into_iter()
+        .map(|to| Destination { address: to.to_index() })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string(&res).unwrap_or_else(|_| "[]".to_string())
+}
+
+// plays a move clicked on the webview grid and reports the whole new state,
+// an illegal move leaves the board as it was and fills in "error"
+#[cfg(feature = "json")]
+pub fn handle_gui_move(board: &mut Board, from_idx: usize, to_idx: usize) -> String {
+    #[derive(serde::Serialize)]
+    struct GuiState {
+        pieces: Vec<Option<Piece>>,
+        whose_turn: Color,
+        status: GameStatus,
+        captured: Option<Piece>,
+        error: Option<String>,
+    }
+
+    let to_address = |index: usize| -> Result<Address, MoveError> {
+        u8::try_from(index).ok()
+            .and_then(Address::from_index)
+            .ok_or(MoveError::InvalidAddress(ParseAddressError::OutOfBoard(index)))
+    };
+
+    let result = to_address(from_idx)
+        .and_then(|from| Ok((from, to_address(to_idx)?)))
+        .and_then(|(from, to)| make_move_coords(board, from, to));
+
+    let (captured, error) = match result {
+        Ok(()) => (board.history.last().and_then(|record| record.captured), None),
+        Err(error) => (None, Some(error.to_string())),
+    };
+
+    let state = GuiState {
+        pieces: (0..CELLS_COUNT).filter_map(Address::from_index).map(|addr| *board.get_cell(addr)).collect(),
+        whose_turn: board.whose_turn,
+        status: game_status(board),
+        captured,
+        error,
+    };
+
+    serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+    use super::super::test_support::assert_moves;
+
+    #[test]
+    fn board_moves() {
+        let board = Board::new();
+        let res = get_piece_moves(&board, Address::from_str("e2").unwrap());
+        assert!(res.is_ok());
+
+        println!("{:?}", res.unwrap());
+    }
+
+    #[test]
+    fn pawn_long_march_blocked() {
+        let mut board = Board::new();
+        assert!(make_move_coords(&mut board, Address::parse("b1"), Address::parse("c3")).is_ok());
+        assert!(make_move_coords(&mut board, Address::parse("d7"), Address::parse("d6")).is_ok());
+
+        let moves = get_legal_moves(&board, Address::parse("c2")).unwrap();
+        assert!(moves.is_empty());
+    }
+
+    fn put(board: &mut Board, addr: &str, color: Color, piece_type: PieceType) {
+        board.set_piece(Address::parse(addr), Some(Piece { piece_type, color }));
+    }
+
+    #[test]
+    fn make_move_errors() {
+        let mut board = Board::new();
+        assert_eq!(
+            make_move_coords(&mut board, Address::parse("e4"), Address::parse("e5")),
+            Err(MoveError::NoPiece)
+        );
+        assert_eq!(
+            make_move_coords(&mut board, Address::parse("e7"), Address::parse("e5")),
+            Err(MoveError::WrongColorTurn(Color::Black))
+        );
+        assert_eq!(
+            make_moves(&mut board, vec![("e2", "e4"), ("e4", "e5")]),
+            Err(MoveError::WrongColorTurn(Color::White))
+        );
+        assert_eq!(
+            make_moves(&mut board, vec![("e7", "e9")]),
+            Err(MoveError::InvalidAddress(ParseAddressError::BadRank('9')))
+        );
+    }
+
+    #[test]
+    fn sequence_statuses() {
+        let uci = |moves: &[&str]| -> Vec<Move> {
+            moves.iter()
+                .map(|mv| parse_uci_move(mv).map(|(from, to, promotion)| Move::new(from, to, promotion)).unwrap())
+                .collect()
+        };
+
+        let mut board = Board::new();
+        let statuses = play_sequence(&mut board, &uci(&["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"]));
+        let mut expected = vec![GameStatus::Ongoing; 6];
+        expected.push(GameStatus::Checkmate);
+        assert_eq!(statuses, Ok(expected));
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            play_sequence(&mut board, &uci(&["a1a8", "e8e7", "a8a7", "e7e6", "a7a8"])),
+            Ok(vec![GameStatus::Check, GameStatus::Ongoing, GameStatus::Check, GameStatus::Ongoing, GameStatus::Ongoing])
+        );
+
+        // stops at the illegal move with the statuses before it
+        let mut board = Board::new();
+        assert_eq!(
+            play_sequence(&mut board, &uci(&["f2f3", "e7e5", "e1e3", "g2g4", "d8h4"])),
+            Err((vec![GameStatus::Ongoing; 2], MoveError::UnreachableMove { from: Address::parse("e1"), to: Address::parse("e3") }))
+        );
+        assert_eq!(board.history.len(), 2);
+        assert_eq!(play_sequence(&mut board, &[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn move_error_messages() {
+        let message = |fen: &str, mv: &str| {
+            let mut board = Board::from_fen(fen).unwrap();
+            let (from, to, promotion) = match parse_uci_move(mv) {
+                Ok(mv) => mv,
+                Err(error) => return error.to_string(),
+            };
+            make_move(&mut board, Move::new(from, to, promotion)).unwrap_err().to_string()
+        };
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        assert_eq!(message(start, "e2e5"), "e2 cannot reach e5");
+        assert_eq!(message(start, "e7e5"), "it is White's turn");
+        assert_eq!(message("4k3/8/8/8/8/8/8/4K3 b - - 0 1", "e1e2"), "it is Black's turn");
+        assert_eq!(message(start, "e4e5"), "there is no piece to move");
+        assert_eq!(message("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1", "d2f3"), "the move leaves the king in check");
+        assert_eq!(message("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8k"), "a pawn cannot promote to a king");
+        assert_eq!(message("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8x"), "'x' is not a piece to promote to");
+        assert_eq!(message(start, "e2e9"), "invalid square: '9' is not a rank from 1 to 8");
+        assert_eq!(message(start, "j2e4"), "invalid square: 'j' is not a file from a to h");
+        assert_eq!(MoveError::GameOver.to_string(), "the game is over");
+
+        // usable as a boxed error
+        let error: Box<dyn std::error::Error> = Box::new(MoveError::NoPiece);
+        assert_eq!(error.to_string(), "there is no piece to move");
+    }
+
+    #[test]
+    fn castling_availability() {
+        let can = |fen: &str, color: Color, side: Side| can_castle(&Board::from_fen(fen).unwrap(), color, side);
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        for color in [Color::White, Color::Black] {
+            assert!(can(fen, color, Side::King));
+            assert!(can(fen, color, Side::Queen));
+        }
+        assert!(!can(&Board::new().to_fen(), Color::White, Side::King));
+
+        // no right, a piece in between, in check, through check, and into check
+        assert!(!can("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1", Color::White, Side::King));
+        assert!(can("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1", Color::White, Side::Queen));
+        assert!(!can("r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1", Color::White, Side::Queen));
+        assert!(!can("r3k2r/8/8/8/8/8/8/R3K1NR w KQkq - 0 1", Color::White, Side::King));
+        assert!(!can("r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 0 1", Color::White, Side::King));
+        assert!(!can("r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 0 1", Color::White, Side::Queen));
+        assert!(!can("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1", Color::White, Side::King));
+        assert!(can("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1", Color::White, Side::Queen));
+        assert!(!can("r3k2r/8/8/8/8/8/6r1/R3K2R w KQkq - 0 1", Color::White, Side::King));
+        // the rook may pass an attacked square on the queen side
+        assert!(can("r3k2r/8/8/8/8/8/1r6/R3K2R w KQkq - 0 1", Color::White, Side::Queen));
+
+        // a loaded position with the rights but the pieces elsewhere
+        assert!(!can("r3k2r/8/8/8/8/8/8/R4K1R w KQkq - 0 1", Color::White, Side::King));
+        assert!(!can("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1", Color::White, Side::King));
+
+        // the turn does not matter
+        assert!(can("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", Color::Black, Side::Queen));
+
+        // chess960 with the king on b1 between rooks on a1 and f1, a knight on e1 in the way
+        let board = Board::from_fen("rk3r2/8/8/8/8/8/8/RK2NR2 w FAfa - 0 1").unwrap();
+        assert!(can_castle(&board, Color::White, Side::Queen));
+        assert!(!can_castle(&board, Color::White, Side::King));
+        // the black king would cross f8, where its rook stands attacked by the f1 one
+        assert!(can_castle(&board, Color::Black, Side::Queen));
+        assert!(!can_castle(&board, Color::Black, Side::King));
+    }
+
+    #[test]
+    fn move_validation() {
+        let board = Board::new();
+        let moves = vec![("e2", "e4"), ("e7", "e5"), ("e4", "e5"), ("g1", "f3"), ("e9", "e5"), ("g1", "f3"), ("b8", "c6")];
+
+        assert_eq!(validate_moves(&board, moves), vec![
+            (2, MoveError::UnreachableMove { from: Address::parse("e4"), to: Address::parse("e5") }),
+            (4, MoveError::InvalidAddress(ParseAddressError::BadRank('9'))),
+            (5, MoveError::NoPiece),
+        ]);
+        assert!(board == Board::new());
+
+        assert!(validate_moves(&board, vec![("e2", "e4"), ("e7", "e5")]).is_empty());
+        assert!(validate_moves(&board, vec![]).is_empty());
+    }
+
+    #[test]
+    fn square_attacked() {
+        let mut board = Board::new_empty();
+        put(&mut board, "e4", Color::White, PieceType::Pawn);
+        put(&mut board, "b1", Color::Black, PieceType::Knight);
+        put(&mut board, "h8", Color::Black, PieceType::Rook);
+        put(&mut board, "h5", Color::White, PieceType::Bishop);
+
+        assert!(is_square_attacked(&board, Address::parse("d5"), Color::White));
+        assert!(is_square_attacked(&board, Address::parse("f5"), Color::White));
+        assert!(!is_square_attacked(&board, Address::parse("e5"), Color::White));
+        assert!(is_square_attacked(&board, Address::parse("d2"), Color::Black));
+        assert!(is_square_attacked(&board, Address::parse("h6"), Color::Black));
+        // the bishop on h5 blocks the rook ray
+        assert!(!is_square_attacked(&board, Address::parse("h4"), Color::Black));
+        assert!(is_square_attacked(&board, Address::parse("e8"), Color::White));
+    }
+
+    #[test]
+    fn attack_map() {
+        let index = |s: &str| Address::parse(s).to_index() as usize;
+
+        let board = Board::new();
+        let attacked = attacked_squares(&board, Color::White);
+        let count = attacked.iter().filter(|a| **a).count();
+        // whole third rank plus every piece of the first two ranks defended by another
+        assert_eq!(count, 22);
+        assert!(attacked[index("e3")]);
+        assert!(!attacked[index("e4")]);
+
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/3q4/8/2P5/3R4/4K3 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            for color in [Color::White, Color::Black] {
+                let attacked = attacked_squares(&board, color);
+                for index in 0..CELLS_COUNT {
+                    let addr = Address::from_index(index).unwrap();
+                    assert_eq!(attacked[index as usize], is_square_attacked(&board, addr, color), "{} {}", fen, addr);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_control() {
+        let index = |s: &str| Address::parse(s).to_index() as usize;
+
+        // the start position is contested equally, the center by nobody yet
+        let control = control_count(&Board::new());
+        for square in ["d4", "e4", "d5", "e5"] {
+            assert_eq!(control[index(square)], 0);
+        }
+        assert_eq!(control[index("d3")], 2);
+        assert_eq!(control[index("d6")], -2);
+        // f3 by the g1 knight and the e2 and g2 pawns
+        assert_eq!(control[index("f3")], 3);
+        assert_eq!(control.iter().map(|&count| count as i32).sum::<i32>(), 0);
+
+        let mut board = Board::new();
+        board.apply_uci_moves(&["e2e4", "d7d5"]).unwrap();
+        let control = control_count(&board);
+        // the e4 pawn against the d8 queen on d5, nothing backs the e4 pawn up
+        assert_eq!(control[index("d5")], 1 - 1);
+        assert_eq!(control[index("e4")], -1);
+        assert_eq!(control[index("f5")], 1 - 1);
+
+        // the same counts attackers_of finds for each side
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let control = control_count(&board);
+        for address in (0..CELLS_COUNT).filter_map(Address::from_index) {
+            let expected = attackers_of(&board, address, Color::White).len() as i8 - attackers_of(&board, address, Color::Black).len() as i8;
+            assert_eq!(control[address.to_index() as usize], expected, "{}", address);
+        }
+    }
+
+    #[test]
+    fn hanging() {
+        assert!(hanging_pieces(&Board::new(), Color::White).is_empty());
+
+        // the d5 knight is attacked and undefended, the c3 knight is defended by the b2 pawn
+        let board = Board::from_fen("4k3/8/4p3/3N4/8/2N5/1P6/4K3 b - - 0 1").unwrap();
+        assert_eq!(hanging_pieces(&board, Color::White), vec![Address::parse("d5")]);
+
+        // a defended rook is still lost to a pawn
+        let board = Board::from_fen("4k3/8/8/2p5/3R4/8/3Q4/4K3 w - - 0 1").unwrap();
+        assert_eq!(hanging_pieces(&board, Color::White), vec![Address::parse("d4")]);
+
+        // a defended pawn attacked by a queen is fine, and the checked king is never listed
+        let board = Board::from_fen("4k3/8/8/3q4/4P3/5P2/8/4K2r w - - 0 1").unwrap();
+        assert!(hanging_pieces(&board, Color::White).is_empty());
+
+        // a rook next to the enemy king is lost unless defended, as the king cannot recapture into defence
+        let board = Board::from_fen("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1").unwrap();
+        assert_eq!(hanging_pieces(&board, Color::Black), vec![Address::parse("d2")]);
+        let board = Board::from_fen("8/8/8/8/8/4k3/3r4/4K3 w - - 0 1").unwrap();
+        assert!(hanging_pieces(&board, Color::Black).is_empty());
+    }
+
+    #[test]
+    fn single_move_legality() {
+        let addr = Address::parse;
+        let board = Board::new();
+        assert_eq!(is_legal_move(&board, addr("e2"), addr("e4")), Ok(true));
+        assert_eq!(is_legal_move(&board, addr("g1"), addr("f3")), Ok(true));
+        assert_eq!(is_legal_move(&board, addr("g1"), addr("e2")), Ok(false));
+        assert_eq!(is_legal_move(&board, addr("a1"), addr("a3")), Ok(false));
+        assert_eq!(is_legal_move(&board, addr("e4"), addr("e5")), Err(MoveError::NoPiece));
+        assert_eq!(is_legal_move(&board, addr("e7"), addr("e5")), Err(MoveError::WrongColorTurn(Color::Black)));
+
+        // the pinned knight may not move, the king may not castle through check
+        let board = Board::from_fen("4r1k1/8/8/8/8/8/4N3/4K2R w K - 0 1").unwrap();
+        assert_eq!(is_legal_move(&board, addr("e2"), addr("c3")), Ok(false));
+        let board = Board::from_fen("5rk1/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(is_legal_move(&board, addr("e1"), addr("g1")), Ok(false));
+        let board = Board::from_fen("6k1/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(is_legal_move(&board, addr("e1"), addr("g1")), Ok(true));
+
+        // agrees with the full generator everywhere
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for (from, piece) in board.iter_pieces().filter(|(_, piece)| piece.color == Color::White) {
+            let legal = get_legal_moves(&board, from).unwrap();
+            for to in (0..CELLS_COUNT).filter_map(Address::from_index) {
+                assert_eq!(is_legal_move(&board, from, to), Ok(legal.contains(&to)), "{:?} {} {}", piece, from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn capture_moves() {
+        let addr = Address::parse;
+        assert!(get_capture_moves(&Board::new(), Color::White).is_empty());
+
+        // en passant and a capturing promotion, the pushes are left out
+        let board = Board::from_fen("1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let mut captures = get_capture_moves(&board, Color::White);
+        captures.sort_by_key(|(from, to)| (from.to_index(), to.to_index()));
+        assert_eq!(captures, vec![(addr("e5"), addr("d6")), (addr("a7"), addr("b8"))]);
+
+        // a capture leaving the king in check is not legal
+        let board = Board::from_fen("4r1k1/8/8/8/8/3p4/4B3/4K3 w - - 0 1").unwrap();
+        assert!(get_capture_moves(&board, Color::White).is_empty());
+
+        // matches the capturing subset of all legal moves
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for color in [Color::White, Color::Black] {
+            let expected = get_all_legal_moves(&board, color).into_iter()
+                .filter(|(_, to)| matches!(board.get_cell(*to), Some(piece) if piece.color != color))
+                .collect::<Vec<_>>();
+            assert_eq!(get_capture_moves(&board, color), expected);
+        }
+    }
+
+    #[test]
+    fn pins() {
+        // two friendly pieces stand between the a5 bishop and the king
+        let board = Board::from_fen("4r1k1/8/8/b7/8/2N5/3PR3/q3K2r w - - 0 1").unwrap();
+        let pins = pinned_pieces(&board, Color::White);
+        assert_eq!(pins, vec![
+            (Address::parse("e2"), (0, -1)),
+        ]);
+
+        let board = Board::from_fen("6k1/8/8/8/1b6/8/3P4/qN2K1Br w - - 0 1").unwrap();
+        let mut pins = pinned_pieces(&board, Color::White);
+        pins.sort_by_key(|(addr, _)| (addr.col, addr.row));
+        assert_eq!(pins, vec![
+            (Address::parse("b1"), (1, 0)),
+            (Address::parse("d2"), (1, -1)),
+            (Address::parse("g1"), (-1, 0)),
+        ]);
+
+        // a knight cannot pin and a rook does not pin diagonally
+        let board = Board::from_fen("6k1/8/8/8/4n3/2r1P3/3P4/4K3 w - - 0 1").unwrap();
+        let pins = pinned_pieces(&board, Color::White);
+        assert!(pins.is_empty());
+    }
+
+    #[test]
+    fn legal_moves_king_safety() {
+        let mut board = Board::new_empty();
+        put(&mut board, "e1", Color::White, PieceType::King);
+        put(&mut board, "e2", Color::White, PieceType::Knight);
+        put(&mut board, "e8", Color::Black, PieceType::Rook);
+        put(&mut board, "d8", Color::Black, PieceType::Rook);
+
+        // pinned knight has nowhere to go
+        let knight_moves = get_legal_moves(&board, Address::parse("e2")).unwrap();
+        assert!(knight_moves.is_empty());
+
+        // king may not step onto the d-file or stay on the e-file
+        let mut king_moves = get_legal_moves(&board, Address::parse("e1")).unwrap();
+        king_moves.sort_by_key(|a| (a.col, a.row));
+        assert_eq!(king_moves, vec![Address::parse("f1"), Address::parse("f2")]);
+
+        assert_eq!(
+            make_move_coords(&mut board, Address::parse("e2"), Address::parse("c3")),
+            Err(MoveError::KingInCheck)
+        );
+        assert_eq!(
+            make_move_coords(&mut board, Address::parse("e1"), Address::parse("d1")),
+            Err(MoveError::KingInCheck)
+        );
+    }
+
+    #[test]
+    fn legal_moves_capture_checker() {
+        let mut board = Board::new_empty();
+        put(&mut board, "e1", Color::White, PieceType::King);
+        put(&mut board, "e2", Color::Black, PieceType::Queen);
+        put(&mut board, "a8", Color::White, PieceType::Rook);
+        put(&mut board, "h8", Color::Black, PieceType::King);
+
+        // king captures the unprotected checker
+        let king_moves = get_legal_moves(&board, Address::parse("e1")).unwrap();
+        assert_eq!(king_moves, vec![Address::parse("e2")]);
+
+        // rook cannot help since it neither blocks nor captures
+        let rook_moves = get_legal_moves(&board, Address::parse("a8")).unwrap();
+        assert!(rook_moves.is_empty());
+
+        assert!(make_move_coords(&mut board, Address::parse("e1"), Address::parse("e2")).is_ok());
+    }
+
+    fn castling_board() -> Board {
+        let mut board = Board::new_empty();
+        put(&mut board, "e1", Color::White, PieceType::King);
+        put(&mut board, "a1", Color::White, PieceType::Rook);
+        put(&mut board, "h1", Color::White, PieceType::Rook);
+        put(&mut board, "e8", Color::Black, PieceType::King);
+        put(&mut board, "a8", Color::Black, PieceType::Rook);
+        put(&mut board, "h8", Color::Black, PieceType::Rook);
+        board.castling_rights = [true; 4];
+        board
+    }
+
+    #[test]
+    fn castling_moves() {
+        let mut board = castling_board();
+        let moves = get_legal_moves(&board, Address::parse("e1")).unwrap();
+        assert!(moves.contains(&Address::parse("g1")));
+        assert!(moves.contains(&Address::parse("c1")));
+
+        // blocked queen side
+        put(&mut board, "b1", Color::White, PieceType::Knight);
+        let moves = get_legal_moves(&board, Address::parse("e1")).unwrap();
+        assert!(moves.contains(&Address::parse("g1")));
+        assert!(!moves.contains(&Address::parse("c1")));
+
+        // king would pass through an attacked f1
+        put(&mut board, "f8", Color::Black, PieceType::Rook);
+        let moves = get_legal_moves(&board, Address::parse("e1")).unwrap();
+        assert!(!moves.contains(&Address::parse("g1")));
+
+        // no castling out of check
+        let mut board = castling_board();
+        put(&mut board, "e5", Color::Black, PieceType::Rook);
+        let moves = get_legal_moves(&board, Address::parse("e1")).unwrap();
+        assert!(!moves.contains(&Address::parse("g1")));
+        assert!(!moves.contains(&Address::parse("c1")));
+    }
+
+    #[test]
+    fn castling_make_move() {
+        let mut board = castling_board();
+        assert!(make_move_coords(&mut board, Address::parse("e1"), Address::parse("g1")).is_ok());
+        assert_eq!(board.get_cell(Address::parse("f1")).unwrap().piece_type, PieceType::Rook);
+        assert!(board.get_cell(Address::parse("h1")).is_none());
+        assert_eq!(board.castling_rights, [false, false, true, true]);
+
+        assert!(make_move_coords(&mut board, Address::parse("e8"), Address::parse("c8")).is_ok());
+        assert_eq!(board.get_cell(Address::parse("d8")).unwrap().piece_type, PieceType::Rook);
+        assert!(board.get_cell(Address::parse("a8")).is_none());
+        assert_eq!(board.castling_rights, [false; 4]);
+    }
+
+    #[test]
+    fn castling_rights_lost() {
+        let mut board = castling_board();
+        assert!(make_move_coords(&mut board, Address::parse("h1"), Address::parse("h8")).is_ok());
+        assert_eq!(board.castling_rights, [false, true, false, true]);
+
+        assert!(make_move_coords(&mut board, Address::parse("e8"), Address::parse("e7")).is_ok());
+        assert_eq!(board.castling_rights, [false, true, false, false]);
+    }
+
+    #[test]
+    fn castling_chess960() {
+        // king on b1 between rooks on a1 and f1, castling is encoded as the king taking its rook
+        let mut board = Board::from_fen("rk3r2/pppppppp/8/8/8/8/PPPPPPPP/RK3R2 w FAfa - 0 1").unwrap();
+
+        let moves = get_legal_moves(&board, Address::parse("b1")).unwrap();
+        assert!(moves.contains(&Address::parse("f1")));
+        // queen side needs the a1 rook to reach d1 across b1 and c1, which is free
+        assert!(moves.contains(&Address::parse("a1")));
+
+        let fen = board.to_fen();
+        assert!(make_move_coords(&mut board, Address::parse("b1"), Address::parse("a1")).is_ok());
+        assert_eq!(board.get_cell(Address::parse("c1")).unwrap().piece_type, PieceType::King);
+        assert_eq!(board.get_cell(Address::parse("d1")).unwrap().piece_type, PieceType::Rook);
+        assert!(board.get_cell(Address::parse("a1")).is_none());
+        assert!(board.get_cell(Address::parse("b1")).is_none());
+        assert_eq!(board.castling_rights, [false, false, true, true]);
+
+        board.undo_move();
+        assert_eq!(board.to_fen(), fen);
+
+        // the king stays on g1 and only the rook moves
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/5RK1 w F - 0 1").unwrap();
+        assert!(make_move_coords(&mut board, Address::parse("g1"), Address::parse("f1")).is_ok());
+        assert_eq!(board.get_cell(Address::parse("c1")).unwrap().piece_type, PieceType::King);
+        assert_eq!(board.get_cell(Address::parse("d1")).unwrap().piece_type, PieceType::Rook);
+        board.undo_move();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/5RK1 w F - 0 1");
+    }
+
+    #[test]
+    fn perft_chess960() {
+        let mut board = Board::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(perft(&mut board, 1), 21);
+        assert_eq!(perft(&mut board, 2), 528);
+        assert_eq!(perft(&mut board, 3), 12189);
+
+        let mut board = Board::from_fen("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9").unwrap();
+        assert_eq!(perft(&mut board, 1), 21);
+        assert_eq!(perft(&mut board, 2), 807);
+        assert_eq!(perft(&mut board, 3), 18002);
+
+        assert_eq!(perft(&mut Board::new_chess960(518).unwrap(), 3), 8902);
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        let mut board = Board::new();
+        let res = make_moves(&mut board, vec![
+            ("e2", "e4"),
+            ("a7", "a6"),
+            ("e4", "e5"),
+            ("d7", "d5"),
+        ]);
+        assert!(res.is_ok());
+        assert_eq!(board.en_passant_target, Some(Address::parse("d6")));
+
+        let moves = get_legal_moves(&board, Address::parse("e5")).unwrap();
+        assert!(moves.contains(&Address::parse("d6")));
+
+        assert!(make_move_coords(&mut board, Address::parse("e5"), Address::parse("d6")).is_ok());
+        assert!(board.get_cell(Address::parse("d5")).is_none());
+        assert_eq!(board.get_cell(Address::parse("d6")).unwrap().piece_type, PieceType::Pawn);
+        assert_eq!(board.black_graveyard.len(), 1);
+        assert_eq!(board.en_passant_target, None);
+    }
+
+    #[test]
+    fn en_passant_expires() {
+        let mut board = Board::new();
+        let res = make_moves(&mut board, vec![
+            ("e2", "e4"),
+            ("a7", "a6"),
+            ("e4", "e5"),
+            ("d7", "d5"),
+            ("h2", "h3"),
+            ("a6", "a5"),
+        ]);
+        assert!(res.is_ok());
+        assert_eq!(board.en_passant_target, None);
+
+        let moves = get_legal_moves(&board, Address::parse("e5")).unwrap();
+        assert!(!moves.contains(&Address::parse("d6")));
+        assert_eq!(
+            make_move_coords(&mut board, Address::parse("e5"), Address::parse("d6")),
+            Err(MoveError::UnreachableMove { from: Address::parse("e5"), to: Address::parse("d6") })
+        );
+    }
+
+    #[test]
+    fn promotion_march() {
+        let mut board = Board::new_empty();
+        put(&mut board, "e1", Color::White, PieceType::King);
+        put(&mut board, "a5", Color::Black, PieceType::King);
+        put(&mut board, "h7", Color::White, PieceType::Pawn);
+        put(&mut board, "c2", Color::Black, PieceType::Pawn);
+
+        assert!(make_move_coords(&mut board, Address::parse("h7"), Address::parse("h8")).is_ok());
+        let piece = board.get_cell(Address::parse("h8")).unwrap();
+        assert_eq!(piece.piece_type, PieceType::Queen);
+        assert_eq!(piece.color, Color::White);
+
+        assert!(make_move_promoting(&mut board, Address::parse("c2"), Address::parse("c1"), PieceType::Knight).is_ok());
+        let piece = board.get_cell(Address::parse("c1")).unwrap();
+        assert_eq!(piece.piece_type, PieceType::Knight);
+        assert_eq!(piece.color, Color::Black);
+    }
+
+    #[test]
+    fn promotion_capture() {
+        let mut board = Board::new_empty();
+        put(&mut board, "e1", Color::White, PieceType::King);
+        put(&mut board, "a8", Color::Black, PieceType::King);
+        put(&mut board, "f7", Color::White, PieceType::Pawn);
+        put(&mut board, "g8", Color::Black, PieceType::Rook);
+
+        assert_eq!(
+            make_move_promoting(&mut board, Address::parse("f7"), Address::parse("g8"), PieceType::King),
+            Err(MoveError::InvalidPromotion(PieceType::King))
+        );
+        assert_eq!(
+            make_move_promoting(&mut board, Address::parse("f7"), Address::parse("g8"), PieceType::Pawn),
+            Err(MoveError::InvalidPromotion(PieceType::Pawn))
+        );
+
+        assert!(make_move_promoting(&mut board, Address::parse("f7"), Address::parse("g8"), PieceType::Rook).is_ok());
+        let piece = board.get_cell(Address::parse("g8")).unwrap();
+        assert_eq!(piece.piece_type, PieceType::Rook);
+        assert_eq!(piece.color, Color::White);
+        assert_eq!(board.black_graveyard.len(), 1);
+    }
+
+    #[test]
+    fn move_counters() {
+        let mut board = Board::new();
+        let res = make_moves(&mut board, vec![
+            ("g1", "f3"),
+            ("g8", "f6"),
+            ("f3", "g1"),
+        ]);
+        assert!(res.is_ok());
+        assert_eq!(board.halfmove_clock, 3);
+        assert_eq!(board.fullmove_number, 2);
+
+        assert!(make_move_coords(&mut board, Address::parse("e7"), Address::parse("e5")).is_ok());
+        assert_eq!(board.to_fen(), "rnbqkb1r/pppp1ppp/5n2/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 3");
+    }
+
+    fn san(fen: &str, from: &str, to: &str) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        move_to_san(&board, Address::parse(from), Address::parse(to))
+    }
+
+    #[test]
+    fn san_basic() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, "e2", "e4"), "e4");
+        assert_eq!(san(start, "g1", "f3"), "Nf3");
+
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(san(fen, "e4", "d5"), "exd5");
+        assert_eq!(san(fen, "f1", "b5"), "Bb5+");
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, "e1", "g1"), "O-O");
+        assert_eq!(san(fen, "e1", "c1"), "O-O-O");
+
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(san(fen, "a1", "a8"), "Ra8#");
+
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "a7", "a8"), "a8=Q+");
+    }
+
+    #[test]
+    fn san_pawn_captures() {
+        // the pawn's own file is always named, even with a single pawn able to take
+        let fen = "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "e4", "d5"), "exd5");
+        assert_eq!(san(fen, "e4", "e5"), "e5");
+
+        // two pawns taking on the same square differ by their files
+        let fen = "4k3/8/8/3p4/2P1P3/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "c4", "d5"), "cxd5");
+        assert_eq!(san(fen, "e4", "d5"), "exd5");
+
+        let fen = "4k3/8/8/8/8/5p2/4P3/4K3 b - - 0 1";
+        assert_eq!(san(fen, "f3", "e2"), "fxe2");
+
+        // en passant lands on an empty square but is still a capture
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+        assert_eq!(san(fen, "e5", "d6"), "exd6");
+        let fen = "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1";
+        assert_eq!(san(fen, "d4", "e3"), "dxe3");
+
+        // promotion by capture with check
+        let fen = "3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "e7", "d8"), "exd8=Q+");
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(move_to_san_promoting(&board, Address::parse("e7"), Address::parse("d8"), PieceType::Knight), "exd8=N");
+        assert_eq!(san_to_move(&board, "exd8=Q+"), Some((Address::parse("e7"), Address::parse("d8"), Some(PieceType::Queen))));
+
+        let fen = "3r2k1/4Pppp/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "e7", "d8"), "exd8=Q#");
+    }
+
+    #[test]
+    fn san_disambiguation() {
+        // knights on b1 and f1 both reach d2: file is enough
+        let fen = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        assert_eq!(san(fen, "b1", "d2"), "Nbd2");
+
+        // rooks on a1 and a5 share the file: rank is needed
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, "a1", "a3"), "R1a3");
+
+        // three queens where neither file nor rank alone is unique
+        let fen = "4k3/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1";
+        assert_eq!(san(fen, "a4", "d4"), "Qa4d4");
+        assert_eq!(san(fen, "h4", "e4"), "Qhe4+");
+
+        // a pinned knight does not count as a rival
+        let fen = "4k3/8/8/8/1b6/8/3N4/4K1N1 w - - 0 1";
+        assert_eq!(san(fen, "g1", "f3"), "Nf3");
+
+        // knights on one file: only the rank tells them apart
+        let fen = "4k3/8/8/6N1/8/8/8/4K1N1 w - - 0 1";
+        assert_eq!(san(fen, "g1", "f3"), "N1f3");
+        assert_eq!(san(fen, "g5", "f3"), "N5f3");
+        assert_eq!(san(fen, "g1", "e2"), "Ne2");
+
+        // queens on a1, a8 and h1: the pair on the a-file takes ranks,
+        // the pair on the first rank and the pair on the long diagonal take files
+        let fen = "Q7/8/8/8/6k1/1K6/8/Q6Q w - - 0 1";
+        assert_eq!(san(fen, "a1", "a3"), "Q1a3");
+        assert_eq!(san(fen, "a8", "a3"), "Q8a3");
+        assert_eq!(san(fen, "a1", "c1"), "Qac1");
+        assert_eq!(san(fen, "h1", "c1"), "Qhc1");
+        assert_eq!(san(fen, "a8", "d5"), "Qad5");
+        assert_eq!(san(fen, "h1", "d5"), "Qhd5");
+        assert_eq!(san(fen, "a1", "c3"), "Qc3");
+
+        // rooks on one rank differ by their files
+        let fen = "4k3/8/8/8/R4R2/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "a4", "c4"), "Rac4");
+
+        // a san round trip over every move of a crowded position stays unambiguous
+        let board = Board::from_fen("1k6/8/8/1N1N4/2Q1Q3/R1N1N2R/8/K7 w - - 0 1").unwrap();
+        for (from, to) in get_all_legal_moves(&board, Color::White) {
+            let san = move_to_san(&board, from, to);
+            assert_eq!(san_to_move(&board, &san), Some((from, to, None)), "{}", san);
+        }
+    }
+
+    #[test]
+    fn status() {
+        let status = |fen: &str| game_status(&Board::from_fen(fen).unwrap());
+
+        assert_eq!(status("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), GameStatus::Ongoing);
+        assert_eq!(status("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1"), GameStatus::Check);
+        assert_eq!(status("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"), GameStatus::Checkmate);
+        assert_eq!(status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameStatus::Draw(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn result_strings() {
+        let result = |fen: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            result_string(game_status(&board), board.whose_turn)
+        };
+
+        // black is mated with black to move: white won
+        assert_eq!(result("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"), "1-0");
+        assert_eq!(result("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"), "0-1");
+        assert_eq!(result("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), "1/2-1/2");
+        assert_eq!(result("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1"), "*");
+        assert_eq!(result("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), "*");
+
+        // a resignation is lost by the resigning side whoever is to move
+        assert_eq!(result_string(GameStatus::Resignation(Color::White), Color::Black), "0-1");
+        assert_eq!(result_string(GameStatus::Resignation(Color::Black), Color::Black), "1-0");
+        assert_eq!(result_string(GameStatus::AgreedDraw, Color::White), "1/2-1/2");
+        assert_eq!(result_string(GameStatus::Draw(DrawReason::FiftyMove), Color::White), "1/2-1/2");
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let mut board = Board::new();
+        assert!(make_move_coords(&mut board, Address::parse("e2"), Address::parse("e4")).is_ok());
+        assert_eq!(board.halfmove_clock(), 0);
+
+        for _ in 0..37 {
+            assert_eq!(game_status(&board), GameStatus::Ongoing);
+
+            let res = make_moves(&mut board, vec![
+                ("g8", "f6"),
+                ("g1", "f3"),
+                ("f6", "g8"),
+                ("f3", "g1"),
+            ]);
+            assert!(res.is_ok());
+        }
+
+        // fifty moves only give a claim, seventy-five end the game
+        assert_eq!(board.halfmove_clock(), 148);
+        assert_eq!(game_status(&board), GameStatus::Ongoing);
+        assert!(make_moves(&mut board, vec![("g8", "f6"), ("g1", "f3")]).is_ok());
+        assert_eq!(board.halfmove_clock(), 150);
+        assert_eq!(game_status(&board), GameStatus::Draw(DrawReason::SeventyFiveMove));
+
+        // a pawn move resets the clock
+        assert!(make_move_coords(&mut board, Address::parse("e7"), Address::parse("e5")).is_ok());
+        assert_eq!(board.halfmove_clock(), 0);
+        assert_eq!(game_status(&board), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn fifty_move_rule_mate_wins() {
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80").unwrap();
+        assert_eq!(game_status(&board), GameStatus::Checkmate);
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 150 80").unwrap();
+        assert_eq!(game_status(&board), GameStatus::Checkmate);
+    }
+
+    #[test]
+    fn perft_start() {
+        let mut board = Board::new();
+        assert_eq!(perft(&mut board, 1), 20);
+        assert_eq!(perft(&mut board, 2), 400);
+        assert_eq!(perft(&mut board, 3), 8902);
+        assert_eq!(board.to_fen(), Board::new().to_fen());
+        assert!(board.history.is_empty());
+        assert!(board.redo_history.is_empty());
+    }
+
+    #[test]
+    fn perft_tricky_positions() {
+        // "kiwipete", castling and en passant heavy
+        let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(perft(&mut board, 1), 48);
+        assert_eq!(perft(&mut board, 2), 2039);
+
+        // discovered checks through en passant
+        let mut board = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&mut board, 1), 14);
+        assert_eq!(perft(&mut board, 2), 191);
+        assert_eq!(perft(&mut board, 3), 2812);
+
+        // promotions
+        let mut board = Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(perft(&mut board, 1), 6);
+        assert_eq!(perft(&mut board, 2), 264);
+    }
+
+    #[test]
+    fn perft_detailed_counts() {
+        let stats = |fen: &str, depth: u32| {
+            let mut board = Board::from_fen(fen).unwrap();
+            let res = perft_detailed(&mut board, depth);
+            assert_eq!(res.nodes, perft(&mut board, depth));
+            assert_eq!(board.to_fen(), fen);
+            res
+        };
+        let table = |nodes, captures, en_passant, castles, promotions, checks, checkmates| {
+            PerftStats { nodes, captures, en_passant, castles, promotions, checks, checkmates }
+        };
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(stats(start, 0), table(1, 0, 0, 0, 0, 0, 0));
+        assert_eq!(stats(start, 1), table(20, 0, 0, 0, 0, 0, 0));
+        assert_eq!(stats(start, 3), table(8902, 34, 0, 0, 0, 12, 0));
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(stats(kiwipete, 1), table(48, 8, 0, 2, 0, 0, 0));
+        assert_eq!(stats(kiwipete, 2), table(2039, 351, 1, 91, 0, 3, 0));
+
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        assert_eq!(stats(fen, 3), table(2812, 209, 2, 0, 0, 267, 0));
+
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        assert_eq!(stats(fen, 2), table(264, 87, 0, 6, 48, 10, 0));
+
+        // mates at the leaves
+        assert_eq!(stats("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1).checkmates, 1);
+    }
+
+    #[test]
+    fn perft_divide_sums() {
+        let mut board = Board::new();
+        let divide = perft_divide(&mut board, 2);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.contains(&("e2e4".to_string(), 20)));
+        assert_eq!(divide.iter().map(|(_, n)| n).sum::<u64>(), 400);
+    }
+
+    #[test]
+    fn san_parse() {
+        let board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K1NR w KQkq - 0 1").unwrap();
+        let addr = Address::parse;
+
+        assert_eq!(san_to_move(&board, "Nf3"), Some((addr("g1"), addr("f3"), None)));
+        assert_eq!(san_to_move(&board, "O-O-O"), Some((addr("e1"), addr("c1"), None)));
+        assert_eq!(san_to_move(&board, "0-0-0"), Some((addr("e1"), addr("c1"), None)));
+        assert_eq!(san_to_move(&board, "Rxa8+!"), Some((addr("a1"), addr("a8"), None)));
+        assert_eq!(san_to_move(&board, "bxa8=N"), Some((addr("b7"), addr("a8"), Some(PieceType::Knight))));
+        assert_eq!(san_to_move(&board, "b8=Q+"), Some((addr("b7"), addr("b8"), Some(PieceType::Queen))));
+        assert_eq!(san_to_move(&board, "O-O"), None);
+        assert_eq!(san_to_move(&board, "Ke3"), None);
+    }
+
+    #[test]
+    fn make_move_struct() {
+        let addr = Address::parse;
+
+        let mut board = Board::new();
+        assert!(make_move(&mut board, Move::new(addr("e2"), addr("e4"), None)).is_ok());
+        assert_eq!(make_move(&mut board, Move::new(addr("e4"), addr("e5"), None)), Err(MoveError::WrongColorTurn(Color::White)));
+
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(make_move(&mut board, Move::new(addr("b7"), addr("b8"), Some(PieceType::Knight))).is_ok());
+        assert_eq!(board.get_cell(addr("b8")).unwrap().piece_type, PieceType::Knight);
+
+        // moves from the detailed generator can be played as they are
+        let mut board = Board::new();
+        let mv = get_legal_moves_detailed(&board, addr("g1")).unwrap()[0];
+        assert!(make_move(&mut board, mv).is_ok());
+        assert_eq!(board.whose_turn, Color::Black);
+    }
+
+    #[test]
+    fn legal_move_sets() {
+        assert_moves!("4k3/8/8/8/8/8/8/4K3 w - - 0 1", ["Kd1", "Kd2", "Ke2", "Kf2", "Kf1"]);
+        assert_moves!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 20);
+
+        // out of check only by the king
+        assert_moves!("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1", ["Kxe2", "Kd1", "Kf1"]);
+        // every promotion piece, castling both ways and en passant
+        assert_moves!("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", ["a8=Q+", "a8=R+", "a8=B", "a8=N", "Kd1", "Kd2", "Ke2", "Kf2", "Kf1"]);
+        assert_moves!("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", 26);
+        assert_moves!("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", ["e6", "exd6", "Kd1", "Kd2", "Ke2", "Kf2", "Kf1"]);
+        // stalemate
+        assert_moves!("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 0);
+    }
+
+    #[test]
+    fn under_promotions() {
+        let fen = "6br/5Ppk/6pp/8/8/8/8/K7 w - - 0 1";
+        assert_moves!(fen, ["f8=Q", "f8=R", "f8=B", "f8=N#", "fxg8=Q+", "fxg8=R", "fxg8=B+", "fxg8=N", "Ka2", "Kb1", "Kb2"]);
+
+        let board = Board::from_fen(fen).unwrap();
+        let knight = get_all_legal_moves_detailed(&board).into_iter()
+            .find(|mv| mv.promotion() == Some(PieceType::Knight) && mv.to == Address::parse("f8"))
+            .unwrap();
+        assert!(move_gives_check(&board, knight));
+        assert_eq!(play_sequence(&mut board.clone(), &[knight]), Ok(vec![GameStatus::Checkmate]));
+
+        // four nodes for every promotion, the counts of a well known promotion perft position
+        let mut board = Board::from_fen("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1").unwrap();
+        assert_eq!(perft(&mut board, 1), 24);
+        assert_eq!(perft(&mut board, 2), 496);
+        assert_eq!(perft(&mut board, 3), 9483);
+    }
+
+    #[test]
+    fn legal_moves_detailed() {
+        let addr = Address::parse;
+        let kind_of = |moves: &[Move], to: &str| -> Vec<MoveKind> {
+            moves.iter().filter(|m| m.to == addr(to)).map(|m| m.kind).collect()
+        };
+
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/4P3/R3K1nR w KQkq d6 0 1").unwrap();
+
+        let moves = get_legal_moves_detailed(&board, addr("e1")).unwrap();
+        assert_eq!(kind_of(&moves, "c1"), vec![MoveKind::Castle(Side::Queen)]);
+        assert_eq!(kind_of(&moves, "d1"), vec![MoveKind::Normal]);
+        // g1 knight blocks the king side
+        assert!(kind_of(&moves, "g1").is_empty());
+
+        let moves = get_legal_moves_detailed(&board, addr("h1")).unwrap();
+        assert_eq!(kind_of(&moves, "g1"), vec![MoveKind::Capture]);
+
+        let moves = get_legal_moves_detailed(&board, addr("e5")).unwrap();
+        assert_eq!(kind_of(&moves, "d6"), vec![MoveKind::EnPassant]);
+        assert_eq!(kind_of(&moves, "e6"), vec![MoveKind::Normal]);
+
+        let moves = get_legal_moves_detailed(&board, addr("e2")).unwrap();
+        assert_eq!(kind_of(&moves, "e3"), vec![MoveKind::Normal]);
+        assert_eq!(kind_of(&moves, "e4"), vec![MoveKind::DoublePawnPush]);
+
+        // every promotion piece, capturing or not
+        let moves = get_legal_moves_detailed(&board, addr("b7")).unwrap();
+        assert_eq!(moves.len(), 8);
+        assert!(kind_of(&moves, "a8").contains(&MoveKind::Promotion(PieceType::Knight)));
+        assert!(kind_of(&moves, "b8").contains(&MoveKind::Promotion(PieceType::Queen)));
+
+        // the same destinations as get_legal_moves
+        let plain = get_legal_moves(&board, addr("a1")).unwrap();
+        let detailed = get_legal_moves_detailed(&board, addr("a1")).unwrap();
+        assert_eq!(detailed.iter().map(|m| m.to).collect::<Vec<_>>(), plain);
+
+        assert_eq!(get_legal_moves_detailed(&board, addr("e4")).unwrap_err(), MoveError::NoPiece);
+
+        // captures by type whatever the kind, a capturing promotion included
+        let type_of = |from: &str, to: &str| -> Vec<MoveType> {
+            get_legal_moves_detailed(&board, addr(from)).unwrap().iter()
+                .filter(|m| m.to == addr(to))
+                .map(|m| m.move_type)
+                .collect()
+        };
+        assert_eq!(type_of("h1", "g1"), vec![MoveType::Capture]);
+        assert_eq!(type_of("e5", "d6"), vec![MoveType::Capture]);
+        assert_eq!(type_of("b7", "a8"), vec![MoveType::Capture; 4]);
+        assert_eq!(type_of("b7", "b8"), vec![MoveType::March; 4]);
+        assert_eq!(type_of("e1", "c1"), vec![MoveType::March]);
+        assert_eq!(type_of("e2", "e4"), vec![MoveType::March]);
+
+        // chess960 castling is written as the king taking its rook but takes nothing
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/1K3R2 w F - 0 1").unwrap();
+        let castle = get_legal_moves_detailed(&board, addr("b1")).unwrap().into_iter()
+            .find(|m| m.to == addr("f1"))
+            .unwrap();
+        assert_eq!((castle.kind, castle.move_type), (MoveKind::Castle(Side::King), MoveType::March));
+    }
+
+    #[test]
+    fn attackers() {
+        let addr = Address::parse;
+        let sorted = |mut v: Vec<Address>| { v.sort_by_key(|a| a.to_index()); v };
+
+        // who can recapture on d5: both pawns and the a8 bishop, but not the queen behind d3
+        let board = Board::from_fen("B3k3/8/5n2/3p4/2P1P3/3P4/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(sorted(attackers_of(&board, addr("d5"), Color::White)), vec![addr("c4"), addr("e4"), addr("a8")]);
+        assert_eq!(attackers_of(&board, addr("d5"), Color::Black), vec![addr("f6")]);
+
+        // the d3 pawn pushes to d4 but does not attack it
+        assert!(attackers_of(&board, addr("d4"), Color::White).is_empty());
+        // a pawn attacks an empty square all the same
+        assert_eq!(attackers_of(&board, addr("c4"), Color::Black), vec![addr("d5")]);
+    }
+
+    #[test]
+    fn legal_attackers() {
+        let addr = Address::parse;
+
+        // the e4 knight is pinned, the b4 pawn takes on c5 but cannot go to the empty a5
+        let board = Board::from_fen("4k3/4r3/8/2p5/1P2N3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(attackers_of(&board, addr("c5"), Color::White), vec![addr("b4"), addr("e4")]);
+        assert_eq!(legal_attackers_of(&board, addr("c5"), Color::White), vec![addr("b4")]);
+        assert_eq!(attackers_of(&board, addr("a5"), Color::White), vec![addr("b4")]);
+        assert!(legal_attackers_of(&board, addr("a5"), Color::White).is_empty());
+
+        // own pieces are never taken
+        assert!(legal_attackers_of(&board, addr("b4"), Color::White).is_empty());
+
+        // the king may not step onto a guarded square
+        let board = Board::from_fen("4k3/3r4/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(attackers_of(&board, addr("d2"), Color::White), vec![addr("e1")]);
+        assert!(legal_attackers_of(&board, addr("d2"), Color::White).is_empty());
+        assert_eq!(legal_attackers_of(&board, addr("f2"), Color::White), vec![addr("e1")]);
+
+        // en passant goes onto the empty target square
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(legal_attackers_of(&board, addr("d6"), Color::White), vec![addr("e5")]);
+    }
+
+    #[test]
+    fn mobility_counts() {
+        let board = Board::new();
+        assert_eq!(pseudo_mobility(&board, Color::White), 20);
+        assert_eq!(pseudo_mobility(&board, Color::Black), 20);
+        assert_eq!(mobility(&board, Color::White), 20);
+        assert_eq!(mobility(&board, Color::Black), 20);
+
+        let by_piece = mobility_by_piece(&board, Color::White);
+        assert_eq!(by_piece.len(), 16);
+        assert!(by_piece.contains(&(Address::parse("g1"), 2)));
+        assert!(by_piece.contains(&(Address::parse("e2"), 2)));
+        assert!(by_piece.contains(&(Address::parse("a1"), 0)));
+
+        // the pinned knight moves pseudo-legally only
+        let board = Board::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(pseudo_mobility(&board, Color::White), 6 + 4);
+        assert_eq!(mobility(&board, Color::White), 4);
+        assert!(mobility_by_piece(&board, Color::White).contains(&(Address::parse("e2"), 0)));
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        // back rank mate
+        let board = Board::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(is_checkmate(&board, Color::Black));
+        assert!(!is_stalemate(&board, Color::Black));
+        assert!(!is_checkmate(&board, Color::White));
+
+        // asked right after the mating move, before anyone looks at the turn
+        let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1").unwrap();
+        assert!(!is_checkmate(&board, Color::Black));
+        make_move_coords(&mut board, Address::parse("d1"), Address::parse("d8")).unwrap();
+        assert!(is_checkmate(&board, Color::Black));
+
+        // the luft square spoils it
+        let board = Board::from_fen("3R2k1/5pp1/7p/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(!is_checkmate(&board, Color::Black));
+
+        // cornered king without a move while white is to move
+        let board = Board::from_fen("k7/8/1Q6/8/8/8/8/6K1 w - - 0 1").unwrap();
+        assert!(is_stalemate(&board, Color::Black));
+        assert!(!is_checkmate(&board, Color::Black));
+        assert!(!is_stalemate(&board, Color::White));
+    }
+
+    #[test]
+    fn gives_check() {
+        let addr = Address::parse;
+        let gives = |fen: &str, from: &str, to: &str, promotion: Option<PieceType>| {
+            let board = Board::from_fen(fen).unwrap();
+            move_gives_check(&board, Move::new(addr(from), addr(to), promotion))
+        };
+
+        // direct checks and a quiet move
+        assert!(gives("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1", "a8", None));
+        assert!(!gives("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1", "a7", None));
+        assert!(!gives("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1", "c3", "d5", None));
+        assert!(gives("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1", "e4", "d6", None));
+
+        // discovered by the bishop when the knight steps aside
+        assert!(gives("7k/8/8/8/3N4/8/1B6/4K3 w - - 0 1", "d4", "b5", None));
+
+        // en passant uncovers the rook along the rank
+        assert!(gives("8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1", "e5", "d6", None));
+
+        // castling checks with the rook
+        assert!(gives("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1", "g1", None));
+
+        // promoting to a knight checks where a queen would not
+        assert!(gives("8/1P6/2k5/8/8/8/8/4K3 w - - 0 1", "b7", "b8", Some(PieceType::Knight)));
+        assert!(!gives("8/1P6/2k5/8/8/8/8/4K3 w - - 0 1", "b7", "b8", Some(PieceType::Queen)));
+
+        // the same answer as playing the move out, for every move of some busy positions
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            for mv in get_all_legal_moves_detailed(&board) {
+                let mut after = board.clone();
+                make_move(&mut after, mv).unwrap();
+                assert_eq!(move_gives_check(&board, mv), is_in_check(&after, board.whose_turn.opposite()), "{} in {}", mv, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn in_check() {
+        assert!(!is_in_check(&Board::new(), Color::White));
+        assert!(!is_in_check(&Board::new(), Color::Black));
+
+        // back rank check by the rook
+        let board = Board::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(is_in_check(&board, Color::Black));
+        assert!(!is_in_check(&board, Color::White));
+
+        // the same rook behind a blocker
+        let board = Board::from_fen("3R1bk1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(!is_in_check(&board, Color::Black));
+
+        // knight check on the white king
+        let board = Board::from_fen("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1").unwrap();
+        assert!(is_in_check(&board, Color::White));
+        assert!(!is_in_check(&board, Color::Black));
+
+        // no king to attack
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R7 w - - 0 1").unwrap();
+        assert!(!is_in_check(&board, Color::White));
+    }
+
+    #[test]
+    fn passed_pawns() {
+        let addr = Address::parse;
+
+        // a-pawn with nothing on the a and b files ahead, h-pawn watched from g6
+        let board = Board::from_fen("4k3/8/6p1/8/P6P/8/8/4K3 w - - 0 1").unwrap();
+        assert!(is_passed_pawn(&board, addr("a4")));
+        assert!(!is_passed_pawn(&board, addr("h4")));
+        // and the black g-pawn is watched by the white h-pawn in turn
+        assert!(!is_passed_pawn(&board, addr("g6")));
+
+        let board = Board::from_fen("4k3/8/8/8/7P/6p1/8/4K3 w - - 0 1").unwrap();
+        // enemy pawns behind do not count
+        assert!(is_passed_pawn(&board, addr("h4")));
+        assert!(is_passed_pawn(&board, addr("g3")));
+
+        // blocked on its own file
+        let board = Board::from_fen("4k3/p7/8/8/P7/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!is_passed_pawn(&board, addr("a4")));
+        assert!(!is_passed_pawn(&board, addr("a7")));
+
+        // not pawns at all
+        assert!(!is_passed_pawn(&board, addr("e1")));
+        assert!(!is_passed_pawn(&board, addr("d4")));
+        assert!(!is_passed_pawn(&Board::new(), addr("e2")));
+    }
+
+    #[test]
+    fn fen_validation() {
+        assert_eq!(validate_fen(&Board::new().to_fen()), Ok(()));
+        assert_eq!(validate_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"), Ok(()));
+        assert_eq!(validate_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"), Ok(()));
+
+        // parse errors come through as they are
+        assert_eq!(validate_fen("8/8 w - - 0 1"), Err(FenError::WrongRankCount(2)));
+
+        assert_eq!(validate_fen("4k3/8/8/8/8/8/8/8 w - - 0 1"), Err(FenError::KingCount { color: Color::White, count: 0 }));
+        assert_eq!(validate_fen("4k3/8/8/8/8/8/8/K3K3 w - - 0 1"), Err(FenError::KingCount { color: Color::White, count: 2 }));
+        assert_eq!(validate_fen("4k2k/8/8/8/8/8/8/4K3 w - - 0 1"), Err(FenError::KingCount { color: Color::Black, count: 2 }));
+
+        assert_eq!(validate_fen("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"), Err(FenError::PawnOnBackRank(Address::parse("a1"))));
+        assert_eq!(validate_fen("4k2p/8/8/8/8/8/8/4K3 w - - 0 1"), Err(FenError::PawnOnBackRank(Address::parse("h8"))));
+
+        // the target is on the wrong rank for the side to move
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1"),
+            Err(FenError::EnPassantMismatch(Address::parse("e3")))
+        );
+        // no pawn has just passed it
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq d3 0 1"),
+            Err(FenError::EnPassantMismatch(Address::parse("d3")))
+        );
+        // the square the pawn came from is taken
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPPNPPP/RNBQKB1R b KQkq e3 0 1"),
+            Err(FenError::EnPassantMismatch(Address::parse("e3")))
+        );
+
+        // from_fen on its own stays lenient
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn doubled_and_isolated_pawns() {
+        assert_eq!(doubled_pawns(&Board::new(), Color::White), 0);
+        assert_eq!(isolated_pawns(&Board::new(), Color::Black), 0);
+
+        // stacked c-pawns next to a d-pawn, an isolated a-pawn and a lone tripled h-file
+        let board = Board::from_fen("4k3/8/7p/2P4p/P1P4p/2PP4/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(doubled_pawns(&board, Color::White), 3);
+        assert_eq!(isolated_pawns(&board, Color::White), 1);
+        assert_eq!(doubled_pawns(&board, Color::Black), 3);
+        assert_eq!(isolated_pawns(&board, Color::Black), 3);
+
+        // the a-pawn is isolated until a b-pawn stands next to it
+        let board = Board::from_fen("4k3/8/8/8/P7/8/1P6/4K3 w - - 0 1").unwrap();
+        assert_eq!(isolated_pawns(&board, Color::White), 0);
+        let board = Board::from_fen("4k3/8/8/8/P7/8/2P5/4K3 w - - 0 1").unwrap();
+        assert_eq!(isolated_pawns(&board, Color::White), 2);
+    }
+
+    #[test]
+    fn bad_pieces() {
+        // the c1 bishop runs on dark squares with d4 and e3, the f1 one on light ones with f3 only
+        let board = Board::from_fen("4k3/8/8/8/3P4/4PP2/8/2B1KB2 w - - 0 1").unwrap();
+        assert!(is_bad_bishop(&board, Address::parse("c1")));
+        assert!(!is_bad_bishop(&board, Address::parse("f1")));
+        // not a bishop
+        assert!(!is_bad_bishop(&board, Address::parse("e1")));
+        assert!(!is_bad_bishop(&board, Address::parse("d4")));
+
+        // an even split is not bad yet, and only the bishop's own pawns count
+        let board = Board::from_fen("4k3/8/8/3p4/3P4/5P2/8/4KB2 w - - 0 1").unwrap();
+        assert!(!is_bad_bishop(&board, Address::parse("f1")));
+        let board = Board::from_fen("4kb2/8/8/4p3/3P4/8/8/4K3 b - - 0 1").unwrap();
+        assert!(is_bad_bishop(&board, Address::parse("f8")));
+
+        let board = Board::from_fen("n3k3/8/8/7N/2N5/8/8/1N2K2n w - - 0 1").unwrap();
+        assert!(is_knight_on_rim(&board, Address::parse("a8")));
+        assert!(is_knight_on_rim(&board, Address::parse("h5")));
+        assert!(is_knight_on_rim(&board, Address::parse("b1")));
+        assert!(is_knight_on_rim(&board, Address::parse("h1")));
+        assert!(!is_knight_on_rim(&board, Address::parse("c4")));
+        assert!(!is_knight_on_rim(&board, Address::parse("e1")));
+        assert!(!is_knight_on_rim(&board, Address::parse("a1")));
+    }
+
+    #[test]
+    fn open_files_detection() {
+        assert!(open_files(&Board::new()).is_empty());
+
+        let board = Board::from_fen("4k3/p7/8/8/7P/8/3P4/R3K3 w - - 0 1").unwrap();
+        assert_eq!(open_files(&board), vec![1, 2, 4, 5, 6]);
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        assert_eq!(open_files(&board), (0..ROW_SIZE).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn file_statuses() {
+        assert!((0..ROW_SIZE).all(|file| file_status(&Board::new(), file) == FileStatus::Closed));
+
+        // a: black pawn only, c: white pawn only, e: both, h: none; the rook and kings do not count
+        let board = Board::from_fen("4k3/p3p3/8/8/2P5/4P3/8/4K2R w - - 0 1").unwrap();
+        assert_eq!(file_status(&board, 0), FileStatus::HalfOpen(Color::White));
+        assert_eq!(file_status(&board, 1), FileStatus::Open);
+        assert_eq!(file_status(&board, 2), FileStatus::HalfOpen(Color::Black));
+        assert_eq!(file_status(&board, 4), FileStatus::Closed);
+        assert_eq!(file_status(&board, 7), FileStatus::Open);
+
+        // the h-file with white pawns only, doubled
+        let board = Board::from_fen("4k3/7P/8/8/8/8/7P/4K3 w - - 0 1").unwrap();
+        assert_eq!(file_status(&board, 7), FileStatus::HalfOpen(Color::Black));
+        assert_eq!(file_status(&board, 0), FileStatus::Open);
+    }
+
+    #[test]
+    fn phase_detection() {
+        assert_eq!(detect_phase(&Board::new()), GamePhase::Opening);
+
+        let mut board = Board::new();
+        make_moves(&mut board, vec![("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("b8", "c6")]).unwrap();
+        assert_eq!(detect_phase(&board), GamePhase::Opening);
+
+        // developed pieces on move 12
+        let board = Board::from_fen("r2q1rk1/ppp2ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPP2PPP/R2Q1RK1 w - - 0 12").unwrap();
+        assert_eq!(detect_phase(&board), GamePhase::Middlegame);
+
+        let board = Board::from_fen("r3k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 40").unwrap();
+        assert_eq!(detect_phase(&board), GamePhase::Endgame);
+
+        // queens traded with the minor pieces still around
+        let board = Board::from_fen("r1b1k2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1B1K2R w KQkq - 0 8").unwrap();
+        assert_eq!(detect_phase(&board), GamePhase::Middlegame);
+        let board = Board::from_fen("r3k2r/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/R3K2R w KQkq - 0 20").unwrap();
+        assert_eq!(detect_phase(&board), GamePhase::Endgame);
+    }
+
+    #[test]
+    fn unchecked_make_unmake() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k3/1P6/8/8/8/8/8/4K3 w q - 0 40",
+        ];
+
+        for fen in fens {
+            let mut board = Board::from_fen(fen).unwrap();
+            for (from, to, promotion) in get_all_legal_moves_promoting(&board) {
+                let mut checked = board.clone();
+                assert!(make_move_promoting(&mut checked, from, to, promotion).is_ok());
+
+                make_move_unchecked_promoting(&mut board, from, to, promotion);
+                assert_eq!(board.to_fen(), checked.to_fen());
+
+                assert!(board.unmake_move().is_some());
+                assert_eq!(board.to_fen(), fen);
+            }
+        }
+
+        let mut board = Board::new();
+        make_move_unchecked(&mut board, Address::parse("g1"), Address::parse("f3"));
+        assert_eq!(board.whose_turn, Color::Black);
+        assert_eq!(board.history.len(), 1);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn random_moves() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let board = Board::new();
+        for _ in 0..20 {
+            let mv = random_move(&board, &mut rng).unwrap();
+            assert!(is_legal_move(&board, mv.from, mv.to).unwrap());
+        }
+
+        // mated side has nothing to play
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(random_move(&board, &mut rng), None);
+
+        // the same seed plays the same game
+        let play = |seed: u64| {
+            let mut board = Board::new();
+            let moves = play_random_game(&mut board, &mut rand::rngs::StdRng::seed_from_u64(seed));
+            (moves, board)
+        };
+        let (moves, mut board) = play(42);
+        assert_eq!(play(42).0, moves);
+        assert!(game_status(&board).is_over());
+        assert_eq!(board.history.len(), moves.len());
+
+        // taking the whole game back returns to the start
+        while board.unmake_move().is_some() {}
+        assert_eq!(board.to_fen(), Board::new().to_fen());
+    }
+
+    #[test]
+    fn display_indices() {
+        let mut board = Board::new();
+        assert_eq!(display_index_to_address(&board, 0), Some(Address::parse("a8")));
+        assert_eq!(display_index_to_address(&board, 52), Some(Address::parse("e2")));
+        assert_eq!(display_index_to_address(&board, 63), Some(Address::parse("h1")));
+        assert_eq!(display_index_to_address(&board, 64), None);
+
+        for flipped in [false, true] {
+            if flipped {
+                board.flip_board();
+            }
+
+            // the cells follow the order Display draws them in
+            let drawn = board.to_string().lines()
+                .filter(|line| line.starts_with(|ch: char| ch.is_ascii_digit()))
+                .flat_map(|line| line.split_whitespace().skip(1).map(str::to_string).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            assert_eq!(drawn.len(), CELLS_COUNT as usize);
+
+            for (index, cell) in drawn.iter().enumerate() {
+                let address = display_index_to_address(&board, index).unwrap();
+                let piece = board.get_cell(address).map_or(".".to_string(), |piece| piece.to_string());
+                assert_eq!(*cell, piece);
+                assert_eq!(address_to_display_index(&board, address), index);
+            }
+        }
+
+        assert_eq!(display_index_to_address(&board, 0), Some(Address::parse("h1")));
+        assert_eq!(display_index_to_address(&board, 11), Some(Address::parse("e2")));
+        assert_eq!(address_to_display_index(&board, Address::parse("a8")), 63);
+
+        // auto-flip follows the side to move
+        board.set_auto_flip(true);
+        assert_eq!(display_index_to_address(&board, 0), Some(Address::parse("a8")));
+        make_move_coords(&mut board, Address::parse("e2"), Address::parse("e4")).unwrap();
+        assert_eq!(display_index_to_address(&board, 0), Some(Address::parse("h1")));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn legal_moves_as_json() {
+        let board = Board::new();
+        assert_eq!(legal_moves_json(&board, Address::parse("e2")), r#"[{"address":20},{"address":28}]"#);
+        assert_eq!(legal_moves_json(&board, Address::parse("g1")), r#"[{"address":21},{"address":23}]"#);
+
+        // empty square, opponent's piece and a piece without moves
+        assert_eq!(legal_moves_json(&board, Address::parse("e4")), "[]");
+        assert_eq!(legal_moves_json(&board, Address::parse("e7")), "[]");
+        assert_eq!(legal_moves_json(&board, Address::parse("a1")), "[]");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn gui_move_json() {
+        let parse = |json: &str| serde_json::from_str::<serde_json::Value>(json).unwrap();
+
+        let mut board = Board::new();
+        let state = parse(&handle_gui_move(&mut board, 12, 28));
+        assert_eq!(state["whose_turn"], "Black");
+        assert_eq!(state["status"], "Ongoing");
+        assert!(state["error"].is_null());
+        assert!(state["pieces"][12].is_null());
+        assert_eq!(state["pieces"][28]["piece_type"], "Pawn");
+        assert_eq!(state["pieces"].as_array().unwrap().len(), 64);
+
+        // e4 is not reachable from e7 in one move and 64 is off the board
+        let state = parse(&handle_gui_move(&mut board, 52, 28));
+        assert_eq!(state["error"], "e7 cannot reach e4");
+        assert_eq!(state["whose_turn"], "Black");
+        let state = parse(&handle_gui_move(&mut board, 52, 64));
+        assert_eq!(state["error"], "invalid square: square index 64 is off the board");
+
+        let mut board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let state = parse(&handle_gui_move(&mut board, 28, 35));
+        assert_eq!(state["captured"]["piece_type"], "Pawn");
+        assert_eq!(state["captured"]["color"], "Black");
+
+        let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let state = parse(&handle_gui_move(&mut board, 0, 56));
+        assert_eq!(state["status"], "Checkmate");
+    }
+
+    #[test]
+    fn uci_moves() {
+        assert_eq!(parse_uci_move("e2e4"), Ok((Address::parse("e2"), Address::parse("e4"), None)));
+        assert_eq!(parse_uci_move("e7e8n"), Ok((Address::parse("e7"), Address::parse("e8"), Some(PieceType::Knight))));
+        assert_eq!(parse_uci_move("e7e8k"), Err(MoveError::InvalidPromotion(PieceType::King)));
+        assert_eq!(parse_uci_move("e7e8x"), Err(MoveError::InvalidPromotionChar('x')));
+        assert_eq!(parse_uci_move("e9e4"), Err(MoveError::InvalidAddress(ParseAddressError::BadRank('9'))));
+        assert_eq!(parse_uci_move("i2e4"), Err(MoveError::InvalidAddress(ParseAddressError::BadFile('i'))));
+        assert_eq!(parse_uci_move("e2"), Err(MoveError::InvalidAddress(ParseAddressError::WrongLength(2))));
+
+        assert_eq!(format_uci_move(Address::parse("g1"), Address::parse("f3"), None), "g1f3");
+        assert_eq!(format_uci_move(Address::parse("a2"), Address::parse("a1"), Some(PieceType::Rook)), "a2a1r");
+    }
+
+    fn check_undo_redo(fen: &str, from: &str, to: &str) {
+        let mut board = Board::from_fen(fen).unwrap();
+        assert!(make_move_coords(&mut board, Address::parse(from), Address::parse(to)).is_ok());
+        let played = board.to_fen();
+        let graveyards = (board.white_graveyard.clone(), board.black_graveyard.clone());
+
+        assert!(board.undo_move());
+        assert_eq!(board.to_fen(), fen);
+        assert!(board.white_graveyard.is_empty());
+        assert!(board.black_graveyard.is_empty());
+        assert!(board.history.is_empty());
+
+        assert!(board.redo_move());
+        assert_eq!(board.to_fen(), played);
+        assert_eq!((board.white_graveyard.clone(), board.black_graveyard.clone()), graveyards);
+        assert!(!board.redo_move());
+    }
+
+    #[test]
+    fn long_game_unwinds_to_start() {
+        // xorshift so the game is reproducible
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for start in [Board::new(), Board::new_chess960(123).unwrap()] {
+            let mut board = start.clone();
+            let mut hashes = vec![board.zobrist_hash()];
+
+            while !game_status(&board).is_over() && board.history.len() < 400 {
+                let moves = get_all_legal_moves_promoting(&board);
+                let (from, to, promotion) = moves[next() as usize % moves.len()];
+                make_move_unchecked_promoting(&mut board, from, to, promotion);
+                hashes.push(board.zobrist_hash());
+            }
+
+            // every unmake is checked against the snapshot in debug builds,
+            // the hashes are compared here in release ones as well
+            while board.unmake_move().is_some() {
+                hashes.pop();
+                assert_eq!(board.zobrist_hash(), *hashes.last().unwrap());
+            }
+
+            assert!(board == start);
+            assert!(board.white_graveyard.is_empty() && board.black_graveyard.is_empty());
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "unmake_move did not restore the position")]
+    fn unmake_check_catches_corruption() {
+        let mut board = Board::new();
+        make_move_unchecked(&mut board, Address::parse("e2"), Address::parse("e4"));
+        board.castling_rights[WHITE_KING_SIDE] = false;
+        board.set_cell(Address::parse("d7"), None);
+        board.unmake_move();
+    }
+
+    #[test]
+    fn undo_redo() {
+        // capture
+        check_undo_redo("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", "e4", "d5");
+        // castling both ways
+        check_undo_redo("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 3 10", "e1", "g1");
+        check_undo_redo("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 10", "e8", "c8");
+        // en passant
+        check_undo_redo("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5", "f6");
+        // promotion with capture
+        check_undo_redo("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 40", "a7", "b8");
+        // rook capture losing castling rights
+        check_undo_redo("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "a1", "a8");
+    }
+
+    #[test]
+    fn undo_sequence() {
+        let mut board = Board::new();
+        let res = make_moves(&mut board, vec![
+            ("e2", "e4"),
+            ("d7", "d5"),
+            ("e4", "d5"),
+            ("d8", "d5"),
+        ]);
+        assert!(res.is_ok());
+
+        while board.undo_move() {}
+        assert_eq!(board.to_fen(), Board::new().to_fen());
+        assert_eq!(board.redo_history.len(), 4);
+
+        // a new move drops the redo line
+        assert!(board.redo_move());
+        assert!(make_move_coords(&mut board, Address::parse("e7"), Address::parse("e5")).is_ok());
+        assert!(board.redo_history.is_empty());
+        assert!(!board.redo_move());
     }
 }
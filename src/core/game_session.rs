@@ -1,3 +1,1067 @@
-pub struct GameSession {
+use super::chess_types::*;
+use super::game_engine::{self, DrawReason, GameStatus, MoveError};
 
-}
\ No newline at end of file
+static PGN_LINE_WIDTH: usize = 80;
+static START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidFen(FenError),
+    IllegalMove{index: usize, san: String},
+}
+
+#[derive(Clone)]
+pub struct Game {
+    // private so that every move and takeback goes through the Game and its caches follow
+    board: Board,
+    // hash of every position since the last capture or pawn move, the current one is the last;
+    // nothing before such a move can ever come back, so repetitions are only counted here
+    position_hashes: Vec<u64>,
+    // SAN of every move of the board history, written when the move is played
+    san_history: Vec<String>,
+    // refreshed after every move so that UI code can poll it for free
+    status: GameStatus,
+    // the color whose draw offer waits for an answer
+    draw_offer: Option<Color>,
+    // what a pawn turns into when a move does not name the piece
+    default_promotion: PieceType,
+
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::from_board(Board::new())
+    }
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_board(board: Board) -> Self {
+        let mut game = Game {
+            position_hashes: vec![board.zobrist_hash()],
+            san_history: Self::get_san_history(&board),
+            status: GameStatus::Ongoing,
+            draw_offer: None,
+            default_promotion: PieceType::Queen,
+            board,
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            round: "?".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        };
+        game.status = game.compute_status();
+        game
+    }
+
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        let mut tags = Vec::<(String, String)>::new();
+        let mut movetext = String::new();
+
+        for line in pgn.lines() {
+            let trimmed = line.trim();
+            if movetext.trim().is_empty() && trimmed.starts_with('[') {
+                if let Some(tag) = Self::parse_tag(trimmed) {
+                    tags.push(tag);
+                }
+            } else {
+                movetext += line;
+                movetext += "\n";
+            }
+        }
+
+        let board = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => Board::new(),
+        };
+
+        let mut game = Game::from_board(board);
+        for (name, value) in tags {
+            match name.as_str() {
+                "Event" => game.event = value,
+                "Site"  => game.site = value,
+                "Date"  => game.date = value,
+                "Round" => game.round = value,
+                "White" => game.white = value,
+                "Black" => game.black = value,
+                _ => {}
+            }
+        }
+
+        for (index, san) in Self::tokenize_movetext(&movetext).into_iter().enumerate() {
+            let illegal = || PgnError::IllegalMove { index, san: san.clone() };
+
+            let (from, to, promotion) = game_engine::san_to_move(&game.board, &san).ok_or_else(illegal)?;
+            let promotion = promotion.unwrap_or(PieceType::Queen);
+            game.make_move_promoting(from, to, promotion).map_err(|_| illegal())?;
+        }
+
+        Ok(game)
+    }
+
+    fn parse_tag(line: &str) -> Option<(String, String)> {
+        let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+        let (name, value) = inner.split_once(char::is_whitespace)?;
+        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+        Some((name.to_string(), value.to_string()))
+    }
+
+    // SAN tokens of the mainline without comments, variations, move numbers, NAGs and the result
+    fn tokenize_movetext(movetext: &str) -> Vec<String> {
+        let mut raw = Vec::<String>::new();
+        let mut current = String::new();
+        let mut chars = movetext.chars();
+
+        while let Some(ch) = chars.next() {
+            let is_separator = ch.is_whitespace() || matches!(ch, '{' | ';' | '(');
+
+            if is_separator && !current.is_empty() {
+                raw.push(std::mem::take(&mut current));
+            }
+
+            match ch {
+                '{' | ';' => Self::skip_comment(ch, &mut chars),
+                '(' => Self::skip_variation(&mut chars),
+                _ if is_separator => {}
+                _ => current.push(ch),
+            }
+        }
+        if !current.is_empty() {
+            raw.push(current);
+        }
+
+        raw.into_iter()
+            .map(|token| {
+                // "12." and "12..." prefixes, possibly glued to the move
+                let rest = token.trim_start_matches(|c: char| c.is_ascii_digit());
+                if rest.starts_with('.') {
+                    rest.trim_start_matches('.').to_string()
+                } else {
+                    token
+                }
+            })
+            .filter(|token| !token.is_empty())
+            .filter(|token| !token.starts_with('$'))
+            .filter(|token| !matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*"))
+            .collect()
+    }
+
+    // the rest of a "{ ... }" or "; ..." comment, its opening char already taken
+    fn skip_comment(start: char, chars: &mut std::str::Chars) {
+        let end = if start == '{' { '}' } else { '\n' };
+        for c in chars.by_ref() {
+            if c == end {
+                break;
+            }
+        }
+    }
+
+    // the rest of a "( ... )" variation with the variations nested in it, its opening
+    // parenthesis already taken. parentheses inside comments do not count
+    fn skip_variation(chars: &mut std::str::Chars) {
+        let mut depth = 1;
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' | ';' => Self::skip_comment(ch, chars),
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    pub fn make_move(&mut self, from: Address, to: Address) -> Result<(), MoveError> {
+        self.make_move_promoting(from, to, self.default_promotion)
+    }
+
+    pub fn default_promotion(&self) -> PieceType {
+        self.default_promotion
+    }
+
+    // pawns and kings are rejected since nothing can promote to them
+    pub fn set_default_promotion(&mut self, piece_type: PieceType) -> Result<(), MoveError> {
+        if matches!(piece_type, PieceType::Pawn | PieceType::King) {
+            return Err(MoveError::InvalidPromotion(piece_type));
+        }
+
+        self.default_promotion = piece_type;
+        Ok(())
+    }
+
+    // no moves are accepted once the game is over, however it ended: by the rules
+    // like a mate or a fivefold repetition, or by the players resigning or agreeing a draw
+    pub fn make_move_promoting(&mut self, from: Address, to: Address, promotion: PieceType) -> Result<(), MoveError> {
+        if self.status.is_over() {
+            return Err(MoveError::GameOver);
+        }
+
+        let mover = self.board.whose_turn;
+        let san = game_engine::move_to_san_promoting(&self.board, from, to, promotion);
+        game_engine::make_move_promoting(&mut self.board, from, to, promotion)?;
+        self.san_history.push(san);
+        if self.board.halfmove_clock == 0 {
+            self.position_hashes.clear();
+        }
+        self.position_hashes.push(self.board.zobrist_hash());
+        self.status = self.compute_status();
+
+        // moving instead of accepting declines the opponent's offer
+        if self.draw_offer == Some(mover.opposite()) {
+            self.draw_offer = None;
+        }
+        Ok(())
+    }
+
+    // false when the game is already over
+    pub fn resign(&mut self, color: Color) -> bool {
+        if self.status.is_over() {
+            return false;
+        }
+
+        self.status = GameStatus::Resignation(color);
+        self.draw_offer = None;
+        true
+    }
+
+    // the offer stands until the opponent accepts it or makes a move
+    pub fn offer_draw(&mut self, color: Color) -> bool {
+        if self.status.is_over() {
+            return false;
+        }
+
+        self.draw_offer = Some(color);
+        true
+    }
+
+    pub fn draw_offer(&self) -> Option<Color> {
+        self.draw_offer
+    }
+
+    // false when there is no offer to accept or the game is already over
+    pub fn accept_draw(&mut self) -> bool {
+        if self.status.is_over() || self.draw_offer.is_none() {
+            return false;
+        }
+
+        self.status = GameStatus::AgreedDraw;
+        self.draw_offer = None;
+        true
+    }
+
+    // an independent copy to try lines on, like an analysis board next to the live game:
+    // its board, history, repetition count and tags are its own, nothing goes back
+    pub fn fork(&self) -> Game {
+        self.clone()
+    }
+
+    // takes the last move back along with its SAN and position hash, the game is judged
+    // afresh so a takeback after a mate or a draw goes on playing; false with no moves
+    pub fn undo_move(&mut self) -> bool {
+        if !self.board.undo_move() {
+            return false;
+        }
+
+        self.san_history.truncate(self.board.history.len());
+        self.position_hashes = Self::get_position_hashes(&self.board);
+        self.status = self.compute_status();
+        self.draw_offer = None;
+        true
+    }
+
+    // hashes of the positions since the last capture or pawn move that led to the board,
+    // the board's own hash last
+    fn get_position_hashes(board: &Board) -> Vec<u64> {
+        let mut board = board.clone();
+        let mut res = vec![board.zobrist_hash()];
+
+        while board.halfmove_clock > 0 && board.undo_move() {
+            res.push(board.zobrist_hash());
+        }
+
+        res.reverse();
+        res
+    }
+
+    // plays the move and reports how the game stands after it
+    pub fn play(&mut self, from: Address, to: Address) -> Result<GameStatus, MoveError> {
+        self.make_move(from, to)?;
+        Ok(self.status)
+    }
+
+    // position setup only: the en passant target and the moves played so far go and the
+    // position counts as new, so it starts the repetition count afresh
+    pub fn set_side_to_move(&mut self, color: Color) {
+        if self.board.whose_turn == color {
+            return;
+        }
+
+        self.board.set_turn(color);
+        self.san_history.clear();
+        self.position_hashes = vec![self.board.zobrist_hash()];
+        self.status = self.compute_status();
+    }
+
+    // every legal (from, to) pair of the side to move
+    pub fn legal_moves(&self) -> Vec<(Address, Address)> {
+        game_engine::get_all_legal_moves(&self.board, self.board.whose_turn)
+    }
+
+    // the latest move with the piece it took, for highlighting its squares
+    pub fn last_move(&self) -> Option<(Move, Option<Piece>)> {
+        self.board.history.last().map(|record| (record.to_move(), record.captured))
+    }
+
+    // number of half-moves played, takebacks not counted
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn current_ply(&self) -> usize {
+        self.board.history.len()
+    }
+
+    // the board as it stood after the first `ply` half-moves, replayed from the start
+    // on every call; a ply past the current one gives the current position
+    pub fn position_at_ply(&self, ply: usize) -> Board {
+        let mut board = Self::get_initial_board(&self.board);
+        board.redo_history.clear();
+
+        for record in self.board.history.iter().take(ply) {
+            let promotion = record.promotion.unwrap_or(PieceType::Queen);
+            game_engine::make_move_unchecked_promoting(&mut board, record.from, record.to, promotion);
+        }
+
+        board
+    }
+
+    pub fn current_fen(&self) -> String {
+        self.board.to_fen()
+    }
+
+    // hashes include castling and en passant rights, so only truly equal positions match
+    fn get_repetition_count(&self) -> usize {
+        match self.position_hashes.last() {
+            Some(current) => self.position_hashes.iter().filter(|h| *h == current).count(),
+            None => 0,
+        }
+    }
+
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.get_repetition_count() >= 3
+    }
+
+    // a draw either player may claim now: a threefold repetition or fifty moves without
+    // a capture or a pawn move; status() only reports draws that need no claim,
+    // a fivefold repetition and seventy-five such moves
+    pub fn draw_claim_available(&self) -> Option<DrawReason> {
+        if self.status.is_over() {
+            None
+        } else if self.is_threefold_repetition() {
+            Some(DrawReason::Repetition)
+        } else if self.board.halfmove_clock >= game_engine::FIFTY_MOVE_RULE_PLIES {
+            Some(DrawReason::FiftyMove)
+        } else {
+            None
+        }
+    }
+
+    // ends the game in a draw when one may be claimed, false otherwise
+    pub fn claim_draw(&mut self) -> bool {
+        match self.draw_claim_available() {
+            Some(reason) => {
+                self.status = GameStatus::Draw(reason);
+                self.draw_offer = None;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    fn compute_status(&self) -> GameStatus {
+        static FIVEFOLD_REPETITION: usize = 5;
+
+        let status = game_engine::game_status(&self.board);
+        match status {
+            GameStatus::Checkmate | GameStatus::Draw(_) => status,
+            _ if self.get_repetition_count() >= FIVEFOLD_REPETITION => GameStatus::Draw(DrawReason::FivefoldRepetition),
+            _ => status,
+        }
+    }
+
+    pub fn result_token(&self) -> &'static str {
+        game_engine::result_string(self.status(), self.board.whose_turn)
+    }
+
+    // every move played so far in SAN, like ["e4", "c6", "Nc3", "d5"]
+    pub fn san_history(&self) -> Vec<String> {
+        self.san_history.clone()
+    }
+
+    // SAN of the moves a board already has in its history, each one
+    // written against the position before it
+    fn get_san_history(board: &Board) -> Vec<String> {
+        let mut board = Self::get_initial_board(board);
+        let mut res = Vec::new();
+
+        while let Some(record) = board.redo_history.last().copied() {
+            let promotion = record.promotion.unwrap_or(PieceType::Queen);
+            res.push(game_engine::move_to_san_promoting(&board, record.from, record.to, promotion));
+            board.redo_move();
+        }
+
+        res
+    }
+
+    // position the recorded history starts from, with the whole game left to redo;
+    // moves already taken back on the board are dropped so they are not redone
+    fn get_initial_board(board: &Board) -> Board {
+        let mut board = board.clone();
+        board.redo_history.clear();
+        while board.undo_move() {}
+        board
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let result = self.result_token();
+        let mut board = Self::get_initial_board(&self.board);
+
+        let mut res = String::new();
+        let tags = [
+            ("Event", self.event.as_str()),
+            ("Site", self.site.as_str()),
+            ("Date", self.date.as_str()),
+            ("Round", self.round.as_str()),
+            ("White", self.white.as_str()),
+            ("Black", self.black.as_str()),
+            ("Result", result),
+        ];
+        for (name, value) in tags {
+            res += &format!("[{} \"{}\"]\n", name, value);
+        }
+
+        let start_fen = board.to_fen();
+        if start_fen != START_FEN {
+            res += "[SetUp \"1\"]\n";
+            res += &format!("[FEN \"{}\"]\n", start_fen);
+        }
+        res += "\n";
+
+        let mut tokens = Vec::<String>::new();
+        for (i, san) in self.san_history.iter().enumerate() {
+            if board.whose_turn == Color::White {
+                tokens.push(format!("{}.", board.fullmove_number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", board.fullmove_number));
+            }
+
+            tokens.push(san.clone());
+            board.redo_move();
+        }
+        tokens.push(result.to_string());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > PGN_LINE_WIDTH {
+                res += "\n";
+                line_len = 0;
+            } else if line_len > 0 {
+                res += " ";
+                line_len += 1;
+            }
+
+            line_len += token.len();
+            res += &token;
+        }
+        res += "\n";
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn play(game: &mut Game, moves: &[(&str, &str)]) {
+        for (from, to) in moves {
+            assert!(game.make_move(Address::parse(from), Address::parse(to)).is_ok());
+        }
+    }
+
+    #[test]
+    fn pgn_export() {
+        let mut game = Game::new();
+        game.white = "Player One".to_string();
+        play(&mut game, &[
+            ("f2", "f3"),
+            ("e7", "e5"),
+            ("g2", "g4"),
+            ("d8", "h4"),
+        ]);
+
+        let pgn = game.to_pgn();
+        assert_eq!(pgn, "\
+[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"Player One\"]
+[Black \"?\"]
+[Result \"0-1\"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+");
+        // exporting does not disturb the live game
+        assert_eq!(game.board().history.len(), 4);
+    }
+
+    #[test]
+    fn pgn_export_after_takeback() {
+        let movetext = |game: &Game| game.to_pgn().lines().last().unwrap().to_string();
+
+        let mut game = Game::new();
+        play(&mut game, &[("e2", "e4"), ("e7", "e5"), ("g1", "f3")]);
+        assert!(game.undo_move());
+        assert_eq!(game.san_history(), vec!["e4", "e5"]);
+        assert_eq!(movetext(&game), "1. e4 e5 *");
+
+        play(&mut game, &[("d2", "d4")]);
+        assert_eq!(game.san_history(), vec!["e4", "e5", "d4"]);
+        assert_eq!(movetext(&game), "1. e4 e5 2. d4 *");
+
+        // a different move in place of the one taken back
+        let mut game = Game::new();
+        play(&mut game, &[("e2", "e4")]);
+        assert!(game.undo_move());
+        play(&mut game, &[("d2", "d4")]);
+        assert_eq!(game.san_history(), vec!["d4"]);
+        assert_eq!(movetext(&game), "1. d4 *");
+
+        // a move taken back before the game starts is not part of it
+        let mut board = Board::new();
+        board.apply_uci_moves(&["e2e4", "e7e5"]).unwrap();
+        assert!(board.undo_move());
+        let game = Game::from_board(board);
+        assert_eq!(game.san_history(), vec!["e4"]);
+        assert_eq!(movetext(&game), "1. e4 *");
+    }
+
+    #[test]
+    fn pgn_export_from_position() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 30").unwrap();
+        let mut game = Game::from_board(board);
+        play(&mut game, &[
+            ("e8", "d7"),
+            ("a1", "a7"),
+        ]);
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 30\"]\n"));
+        assert!(pgn.ends_with("\n30... Kd7 31. Ra7+ *\n"));
+
+        // a chess960 game comes back with its castling rooks
+        let mut game = Game::from_board(Board::new_chess960(100).unwrap());
+        for _ in 0..2 {
+            let (from, to) = game.legal_moves()[0];
+            assert!(game.make_move(from, to).is_ok());
+        }
+        let imported = Game::from_pgn(&game.to_pgn()).unwrap();
+        assert!(*imported.board() == *game.board());
+        assert!(imported.board().chess960);
+        assert_eq!(imported.san_history(), game.san_history());
+    }
+
+    #[test]
+    fn pgn_export_wraps() {
+        let mut game = Game::new();
+        let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+        // the pawn moves keep the shuffling short of a fivefold repetition
+        for pawns in [[("a2", "a3"), ("a7", "a6")], [("b2", "b3"), ("b7", "b6")], [("c2", "c3"), ("c7", "c6")]] {
+            play(&mut game, &shuffle);
+            play(&mut game, &shuffle);
+            play(&mut game, &pawns);
+        }
+
+        let pgn = game.to_pgn();
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= PGN_LINE_WIDTH));
+    }
+
+    #[test]
+    fn san_history() {
+        let mut game = Game::new();
+        play(&mut game, &[("e2", "e4"), ("c7", "c6"), ("b1", "c3"), ("d7", "d5"), ("f1", "b5")]);
+        assert_eq!(game.san_history(), vec!["e4", "c6", "Nc3", "d5", "Bb5"]);
+
+        // an illegal move leaves no trace
+        assert!(game.make_move(Address::parse("e1"), Address::parse("e3")).is_err());
+        play(&mut game, &[("c6", "b5")]);
+        assert_eq!(game.san_history().last().unwrap(), "cxb5");
+        assert_eq!(game.san_history().len(), 6);
+
+        // a board with moves already played gets its history written out
+        let mut board = Board::from_fen("r3k3/6P1/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        game_engine::make_moves(&mut board, vec![("e1", "g1"), ("e8", "c8"), ("g7", "g8")]).unwrap();
+        let game = Game::from_board(board);
+        assert_eq!(game.san_history(), vec!["O-O", "O-O-O", "g8=Q"]);
+
+        let game = Game::from_pgn("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+        assert_eq!(game.san_history(), vec!["f3", "e5", "g4", "Qh4#"]);
+    }
+
+    #[test]
+    fn positions_at_ply() {
+        let mut game = Game::new();
+        assert_eq!(game.current_ply(), 0);
+        assert_eq!(game.position_at_ply(0).to_fen(), START_FEN);
+
+        play(&mut game, &[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("g8", "f6")]);
+        let midpoint = game.current_fen();
+        play(&mut game, &[("d5", "d6"), ("e7", "e5"), ("d6", "c7"), ("e8", "e7"), ("c7", "b8")]);
+        assert_eq!(game.current_ply(), 9);
+
+        assert_eq!(game.position_at_ply(4).to_fen(), midpoint);
+        assert_eq!(game.position_at_ply(2).to_fen(), "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2");
+        assert_eq!(game.position_at_ply(9).to_fen(), game.current_fen());
+        assert_eq!(game.position_at_ply(100).to_fen(), game.current_fen());
+        assert!(game.position_at_ply(9) == *game.board());
+
+        // the replayed board has its own history to take back
+        let mut board = game.position_at_ply(4);
+        assert_eq!(board.history.len(), 4);
+        assert!(board.undo_move());
+        assert_eq!(board.to_fen(), "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2");
+
+        // a game set up from a FEN replays from that position
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        let mut game = Game::from_board(Board::from_fen(fen).unwrap());
+        play(&mut game, &[("e2", "e4"), ("e8", "d7")]);
+        assert_eq!(game.position_at_ply(0).to_fen(), fen);
+        assert_eq!(game.position_at_ply(1).to_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+    }
+
+    #[test]
+    fn last_move() {
+        let mut game = Game::new();
+        assert_eq!(game.last_move(), None);
+
+        let last = |game: &Game| game.last_move().map(|(mv, captured)| (mv.to_string(), mv.kind, captured));
+        let pawn = |color| Some(Piece { piece_type: PieceType::Pawn, color });
+
+        play(&mut game, &[("e2", "e4")]);
+        assert_eq!(last(&game), Some(("e2e4".to_string(), MoveKind::DoublePawnPush, None)));
+        play(&mut game, &[("d7", "d5"), ("e4", "d5")]);
+        assert_eq!(last(&game), Some(("e4d5".to_string(), MoveKind::Capture, pawn(Color::Black))));
+        play(&mut game, &[("e7", "e5"), ("d5", "e6")]);
+        assert_eq!(last(&game), Some(("d5e6".to_string(), MoveKind::EnPassant, pawn(Color::Black))));
+        play(&mut game, &[("a7", "a6"), ("e6", "f7"), ("e8", "e7"), ("f7", "g8")]);
+        assert_eq!(
+            last(&game),
+            Some(("f7g8q".to_string(), MoveKind::Promotion(PieceType::Queen), Some(Piece { piece_type: PieceType::Knight, color: Color::Black })))
+        );
+
+        let mut game = Game::from_board(Board::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap());
+        play(&mut game, &[("e1", "g1")]);
+        assert_eq!(last(&game), Some(("e1g1".to_string(), MoveKind::Castle(Side::King), None)));
+        play(&mut game, &[("e8", "c8")]);
+        assert_eq!(last(&game), Some(("e8c8".to_string(), MoveKind::Castle(Side::Queen), None)));
+
+        // takebacks move it back too
+        game.undo_move();
+        assert_eq!(last(&game), Some(("e1g1".to_string(), MoveKind::Castle(Side::King), None)));
+    }
+
+    #[test]
+    fn takeback() {
+        let mut game = Game::from_pgn("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+        assert_eq!(game.status(), GameStatus::Checkmate);
+
+        assert!(game.undo_move());
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert_eq!(game.board().history.len(), 3);
+        assert_eq!(game.san_history(), vec!["f3", "e5", "g4"]);
+        assert_eq!(game.make_move(Address::parse("d8"), Address::parse("h4")), Ok(()));
+        assert_eq!(game.status(), GameStatus::Checkmate);
+
+        let mut game = Game::new();
+        assert!(!game.undo_move());
+    }
+
+    #[test]
+    fn fork() {
+        let mut game = Game::new();
+        play(&mut game, &[("e2", "e4"), ("e7", "e5")]);
+        game.white = "Alice".to_string();
+        let fen = game.current_fen();
+
+        let mut analysis = game.fork();
+        assert_eq!(analysis.current_fen(), fen);
+        assert_eq!(analysis.san_history(), game.san_history());
+        assert_eq!(analysis.white, "Alice");
+
+        // the fork plays on, takes back and repeats without touching the game
+        play(&mut analysis, &[("d1", "h5"), ("b8", "c6"), ("f1", "c4"), ("g8", "f6"), ("h5", "f7")]);
+        assert_eq!(analysis.status(), GameStatus::Checkmate);
+        assert!(analysis.undo_move());
+        assert!(analysis.undo_move());
+        assert_eq!(analysis.san_history(), vec!["e4", "e5", "Qh5", "Nc6", "Bc4"]);
+        assert_eq!(analysis.status(), GameStatus::Ongoing);
+        analysis.white = "analysis".to_string();
+
+        assert_eq!(game.current_fen(), fen);
+        assert_eq!(game.board().history.len(), 2);
+        assert!(game.board().redo_history.is_empty());
+        assert_eq!(game.san_history(), vec!["e4", "e5"]);
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert_eq!(game.white, "Alice");
+
+        // and the other way round
+        play(&mut game, &[("g1", "f3")]);
+        assert_eq!(analysis.board().history.len(), 5);
+        assert_eq!(analysis.board().redo_history.len(), 2);
+
+        // repetitions are counted apart as well
+        let mut analysis = game.fork();
+        play(&mut analysis, &[("g8", "f6"), ("f3", "g1"), ("f6", "g8"), ("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")]);
+        assert!(analysis.is_threefold_repetition());
+        assert!(!game.is_threefold_repetition());
+
+        assert!(analysis.undo_move());
+        assert!(!analysis.is_threefold_repetition());
+        play(&mut analysis, &[("f6", "g8")]);
+        assert!(analysis.is_threefold_repetition());
+    }
+
+    #[test]
+    fn side_to_move() {
+        // white to move is fine, black to move is mated
+        let mut game = Game::from_board(Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 w - - 0 1").unwrap());
+        assert_eq!(game.status(), GameStatus::Ongoing);
+
+        game.set_side_to_move(Color::Black);
+        assert_eq!(game.board().whose_turn, Color::Black);
+        assert_eq!(game.status(), GameStatus::Checkmate);
+        assert_eq!(game.result_token(), "1-0");
+
+        game.set_side_to_move(Color::White);
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert!(game.play(Address::parse("a8"), Address::parse("b8")).is_ok());
+
+        // the game starts over from the new position, with nothing to take back
+        let mut game = Game::new();
+        play(&mut game, &[("e2", "e4")]);
+        game.set_side_to_move(Color::White);
+        assert_eq!(game.current_ply(), 0);
+        assert!(game.san_history().is_empty());
+        assert!(!game.undo_move());
+        assert_eq!(game.current_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+        play(&mut game, &[("d2", "d4")]);
+        assert_eq!(game.san_history(), vec!["d4"]);
+        assert!(game.to_pgn().contains("[FEN \"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1\"]"));
+    }
+
+    #[test]
+    fn default_promotion() {
+        let fen = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+
+        let mut game = Game::from_board(Board::from_fen(fen).unwrap());
+        assert_eq!(game.default_promotion(), PieceType::Queen);
+        assert!(game.play(Address::parse("b7"), Address::parse("b8")).is_ok());
+        assert_eq!(game.board().get_cell(Address::parse("b8")).unwrap().piece_type, PieceType::Queen);
+
+        let mut game = Game::from_board(Board::from_fen(fen).unwrap());
+        assert!(game.set_default_promotion(PieceType::Knight).is_ok());
+        assert_eq!(game.set_default_promotion(PieceType::King), Err(MoveError::InvalidPromotion(PieceType::King)));
+        assert_eq!(game.set_default_promotion(PieceType::Pawn), Err(MoveError::InvalidPromotion(PieceType::Pawn)));
+        assert_eq!(game.default_promotion(), PieceType::Knight);
+
+        assert!(game.play(Address::parse("b7"), Address::parse("b8")).is_ok());
+        assert_eq!(game.board().get_cell(Address::parse("b8")).unwrap().piece_type, PieceType::Knight);
+
+        // an explicit piece still wins over the default
+        let mut game = Game::from_board(Board::from_fen(fen).unwrap());
+        game.set_default_promotion(PieceType::Bishop).unwrap();
+        assert!(game.make_move_promoting(Address::parse("b7"), Address::parse("b8"), PieceType::Rook).is_ok());
+        assert_eq!(game.board().get_cell(Address::parse("b8")).unwrap().piece_type, PieceType::Rook);
+    }
+
+    #[test]
+    fn play_reports_status() {
+        let mut game = Game::new();
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert_eq!(game.legal_moves().len(), 20);
+
+        assert_eq!(game.play(Address::parse("f2"), Address::parse("f3")), Ok(GameStatus::Ongoing));
+        assert_eq!(game.play(Address::parse("e7"), Address::parse("e5")), Ok(GameStatus::Ongoing));
+        assert_eq!(game.play(Address::parse("g2"), Address::parse("g4")), Ok(GameStatus::Ongoing));
+        assert_eq!(
+            game.play(Address::parse("e1"), Address::parse("e2")),
+            Err(MoveError::WrongColorTurn(Color::White))
+        );
+        assert_eq!(game.play(Address::parse("d8"), Address::parse("h4")), Ok(GameStatus::Checkmate));
+
+        assert_eq!(game.status(), GameStatus::Checkmate);
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(game.current_fen(), "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+    }
+
+    #[test]
+    fn status_of_initial_position() {
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(Game::from_board(board).status(), GameStatus::Draw(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn resignation() {
+        let mut game = Game::new();
+        play(&mut game, &[("e2", "e4")]);
+
+        assert!(game.resign(Color::Black));
+        assert_eq!(game.status(), GameStatus::Resignation(Color::Black));
+        assert_eq!(game.result_token(), "1-0");
+        assert!(game.to_pgn().contains("[Result \"1-0\"]"));
+        assert!(game.to_pgn().ends_with("1. e4 1-0\n"));
+
+        // the game is over for both sides
+        assert!(!game.resign(Color::White));
+        assert!(!game.offer_draw(Color::White));
+        assert_eq!(game.make_move(Address::parse("e7"), Address::parse("e5")), Err(MoveError::GameOver));
+
+        // nobody resigns a finished game
+        let mut game = Game::new();
+        play(&mut game, &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        assert!(!game.resign(Color::White));
+        assert_eq!(game.status(), GameStatus::Checkmate);
+    }
+
+    #[test]
+    fn draw_offer() {
+        let mut game = Game::new();
+        assert!(!game.accept_draw());
+
+        assert!(game.offer_draw(Color::White));
+        assert_eq!(game.draw_offer(), Some(Color::White));
+        // the offering side moving keeps the offer, the opponent moving declines it
+        play(&mut game, &[("e2", "e4")]);
+        assert_eq!(game.draw_offer(), Some(Color::White));
+        play(&mut game, &[("e7", "e5")]);
+        assert_eq!(game.draw_offer(), None);
+        assert!(!game.accept_draw());
+
+        assert!(game.offer_draw(Color::White));
+        assert!(game.accept_draw());
+        assert_eq!(game.status(), GameStatus::AgreedDraw);
+        assert_eq!(game.result_token(), "1/2-1/2");
+        assert!(!game.resign(Color::Black));
+        assert_eq!(game.make_move(Address::parse("g1"), Address::parse("f3")), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = Game::new();
+        let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+
+        play(&mut game, &shuffle);
+        assert!(!game.is_threefold_repetition());
+        assert_eq!(game.status(), GameStatus::Ongoing);
+
+        play(&mut game, &shuffle);
+        assert!(game.is_threefold_repetition());
+        assert_eq!(game.draw_claim_available(), Some(DrawReason::Repetition));
+        // the third time only gives a claim
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert_eq!(game.result_token(), "*");
+
+        let mut claimed = Game::from_board(game.board().clone());
+        assert!(!claimed.claim_draw());
+
+        // taken back and played again, the positions are only counted once
+        let mut replayed = Game::new();
+        play(&mut replayed, &shuffle);
+        for _ in 0..4 {
+            assert!(replayed.undo_move());
+        }
+        play(&mut replayed, &shuffle);
+        assert!(!replayed.is_threefold_repetition());
+        assert_eq!(replayed.draw_claim_available(), None);
+
+        // the fifth time draws on its own
+        play(&mut game, &shuffle);
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        play(&mut game, &shuffle);
+        assert_eq!(game.status(), GameStatus::Draw(DrawReason::FivefoldRepetition));
+        assert_eq!(game.result_token(), "1/2-1/2");
+        assert_eq!(game.draw_claim_available(), None);
+        assert!(!game.claim_draw());
+
+        // and nothing is played after it
+        assert_eq!(game.make_move(Address::parse("e2"), Address::parse("e4")), Err(MoveError::GameOver));
+        assert_eq!(game.status(), GameStatus::Draw(DrawReason::FivefoldRepetition));
+    }
+
+    #[test]
+    fn repetition_window() {
+        let mut game = Game::from_board(Board::from_fen("4k1n1/p7/8/8/8/8/8/R3K1N1 w - - 0 1").unwrap());
+        let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+
+        // twice before the capture and twice after it the knights stand the same way
+        play(&mut game, &shuffle);
+        assert_eq!(game.position_hashes.len(), 5);
+        play(&mut game, &[("a1", "a7"), ("e8", "f8"), ("a7", "a1"), ("f8", "e8")]);
+        assert_eq!(game.position_hashes.len(), 4);
+        play(&mut game, &shuffle);
+        assert!(!game.is_threefold_repetition());
+        assert_eq!(game.draw_claim_available(), None);
+
+        play(&mut game, &shuffle);
+        assert_eq!(game.draw_claim_available(), Some(DrawReason::Repetition));
+
+        // a pawn move starts the window again too
+        let mut game = Game::from_board(Board::new());
+        play(&mut game, &shuffle);
+        play(&mut game, &[("e2", "e4")]);
+        assert_eq!(game.position_hashes.len(), 1);
+    }
+
+    #[test]
+    fn draw_claims() {
+        let mut game = Game::new();
+        let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+        play(&mut game, &shuffle);
+        assert_eq!(game.draw_claim_available(), None);
+        assert!(!game.claim_draw());
+
+        play(&mut game, &shuffle);
+        assert!(game.offer_draw(Color::White));
+        assert!(game.claim_draw());
+        assert_eq!(game.status(), GameStatus::Draw(DrawReason::Repetition));
+        assert_eq!(game.draw_offer(), None);
+        assert_eq!(game.result_token(), "1/2-1/2");
+        assert_eq!(game.make_move(Address::parse("e2"), Address::parse("e4")), Err(MoveError::GameOver));
+
+        // fifty quiet moves can be claimed, seventy-five end the game
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        let mut game = Game::from_board(board);
+        assert_eq!(game.draw_claim_available(), None);
+        play(&mut game, &[("a1", "a2")]);
+        assert_eq!(game.draw_claim_available(), Some(DrawReason::FiftyMove));
+        assert_eq!(game.status(), GameStatus::Ongoing);
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 80").unwrap();
+        let mut game = Game::from_board(board);
+        play(&mut game, &[("a1", "a2")]);
+        assert_eq!(game.status(), GameStatus::Draw(DrawReason::SeventyFiveMove));
+        assert_eq!(game.draw_claim_available(), None);
+        assert_eq!(game.make_move(Address::parse("e8"), Address::parse("d8")), Err(MoveError::GameOver));
+        assert_eq!(game.board().history.len(), 1);
+    }
+
+    #[test]
+    fn repetition_respects_castling_rights() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut game = Game::from_board(board);
+
+        // the first king trip loses castling rights, so the start position never repeats
+        let shuffle = [("e1", "f1"), ("e8", "f8"), ("f1", "e1"), ("f8", "e8")];
+        play(&mut game, &shuffle);
+        play(&mut game, &shuffle);
+        assert!(!game.is_threefold_repetition());
+
+        play(&mut game, &shuffle);
+        assert!(game.is_threefold_repetition());
+    }
+
+    #[test]
+    fn pgn_import() {
+        let pgn = "\
+[Event \"Casual\"]
+[White \"Alice\"]
+[Black \"Bob\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 {aiming at f7} 2... Nc6 3. Qh5 $2 Nf6?? ; falls for it
+4.Qxf7# 1-0
+";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.event, "Casual");
+        assert_eq!(game.white, "Alice");
+        assert_eq!(game.black, "Bob");
+        assert_eq!(game.board().history.len(), 7);
+        assert_eq!(game.result_token(), "1-0");
+        assert_eq!(
+            game.board().to_fen(),
+            "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4"
+        );
+    }
+
+    #[test]
+    fn pgn_import_variations() {
+        let pgn = "\
+[Event \"Annotated\"]
+
+1. e4 e5 2. Nf3 {the main move (not Bc4)} Nc6 (2... d6 3. d4 (3. Bc4 Be7 {solid}) 3... exd4
+(3... Nd7)) 3. Bb5 a6 ; the Morphy defence (
+4. Ba4 (4. Bxc6 dxc6 5. O-O) Nf6 *
+";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.san_history(), vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6"]);
+        assert_eq!(game.result_token(), "*");
+
+        // a variation glued to the moves around it
+        let game = Game::from_pgn("1. d4(1. c4)d5(1... Nf6(1... e6))2. c4").unwrap();
+        assert_eq!(game.san_history(), vec!["d4", "d5", "c4"]);
+    }
+
+    #[test]
+    fn pgn_round_trip() {
+        let pgn = "\
+[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"*\"]
+[SetUp \"1\"]
+[FEN \"r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1\"]
+
+1. bxa8=N O-O 2. O-O-O Rb8 *
+";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.to_pgn(), pgn);
+    }
+
+    #[test]
+    fn pgn_import_errors() {
+        assert_eq!(
+            Game::from_pgn("1. e4 e5 2. Ke3").err(),
+            Some(PgnError::IllegalMove { index: 2, san: "Ke3".to_string() })
+        );
+        assert_eq!(
+            Game::from_pgn("[FEN \"8/8 w - - 0 1\"]\n\n*").err(),
+            Some(PgnError::InvalidFen(FenError::WrongRankCount(2)))
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "rand")]
+use super::book::OpeningBook;
+use super::chess_types::*;
+use super::endgame;
+use super::evaluation;
+use super::game_engine;
+
+// larger than any material sum, reduced by ply so that shorter mates score higher
+static MATE_SCORE: i32 = 1_000_000;
+// how many nodes are searched between two looks at the clock
+static TIME_CHECK_NODES: u64 = 512;
+
+// an optional deadline for the search, once it passes every node returns at once
+struct Limits {
+    deadline: Option<Instant>,
+    nodes: u64,
+    is_stopped: bool,
+}
+
+impl Limits {
+    fn unlimited() -> Self {
+        Limits { deadline: None, nodes: 0, is_stopped: false }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(TIME_CHECK_NODES) {
+            if let Some(deadline) = self.deadline {
+                self.is_stopped |= Instant::now() >= deadline;
+            }
+        }
+        self.is_stopped
+    }
+}
+
+pub fn find_best_move(board: &Board, depth: u32) -> Option<(Address, Address)> {
+    find_best_move_promoting(board, depth).map(|(from, to, _)| (from, to))
+}
+
+// find_best_move together with the piece a promoting pawn turns into,
+// which is not always a queen when a knight gives mate
+pub fn find_best_move_promoting(board: &Board, depth: u32) -> Option<(Address, Address, PieceType)> {
+    get_endgame_move(board)
+        .map(|(from, to)| (from, to, PieceType::Queen))
+        .or_else(|| search_root(&mut board.clone(), depth, None, &mut Limits::unlimited()))
+}
+
+// a king with a queen or a rook against a lone king is played from the mate table,
+// a shallow search cannot see the mate and only shuffles the pieces around
+fn get_endgame_move(board: &Board) -> Option<(Address, Address)> {
+    if game_engine::detect_phase(board) != GamePhase::Endgame {
+        return None;
+    }
+
+    endgame::drive_mate(board).map(|mv| (mv.from, mv.to))
+}
+
+// the same move as find_best_move, with the root moves shared out between `threads`
+// workers that each search their own copy of the board; the best root score found so far
+// is shared between them as the lower bound of the window. The speedup stays below the
+// thread count: moves started before a good score is known get a wider window than the
+// sequential search would give them, and the slowest root move bounds the whole search.
+// At low depths starting the threads dominates, and on a single core it is only slower
+pub fn find_best_move_parallel(board: &Board, depth: u32, threads: usize) -> Option<(Address, Address)> {
+    if let Some(best_move) = get_endgame_move(board) {
+        return Some(best_move);
+    }
+
+    let mut moves = game_engine::get_all_legal_moves_promoting(board);
+    order_moves(board, &mut moves);
+
+    let next_move = AtomicUsize::new(0);
+    let best_score = AtomicI32::new(-MATE_SCORE - 1);
+    let beta = MATE_SCORE + 1;
+
+    let scores = thread::scope(|scope| {
+        let workers = (0..threads.max(1))
+            .map(|_| scope.spawn(|| {
+                let mut board = board.clone();
+                let mut limits = Limits::unlimited();
+                let mut res = Vec::new();
+
+                loop {
+                    let index = next_move.fetch_add(1, Ordering::Relaxed);
+                    let (from, to, promotion) = match moves.get(index) {
+                        Some(mv) => *mv,
+                        None => break,
+                    };
+
+                    // one below the best score, so a move as good as the best still gets
+                    // its exact score and ties go to the earlier move like in find_best_move
+                    let alpha = best_score.load(Ordering::Relaxed) - 1;
+                    game_engine::make_move_unchecked_promoting(&mut board, from, to, promotion);
+                    let score = -negamax(&mut board, depth.saturating_sub(1), 1, -beta, -alpha, &mut limits);
+                    board.unmake_move();
+
+                    best_score.fetch_max(score, Ordering::Relaxed);
+                    res.push((score, index));
+                }
+
+                res
+            }))
+            .collect::<Vec<_>>();
+
+        workers.into_iter()
+            .flat_map(|worker| worker.join().expect("search thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    scores.into_iter()
+        .max_by_key(|&(score, index)| (score, Reverse(index)))
+        .map(|(_, index)| (moves[index].0, moves[index].1))
+}
+
+// iterative deepening within the time budget, returns the best move of the
+// deepest fully searched depth together with that depth;
+// depth 1 is always completed so there is a move whenever one exists
+pub fn find_best_move_timed(board: &Board, max_millis: u64) -> Option<((Address, Address), u32)> {
+    static MAX_DEPTH: u32 = 64;
+
+    // a budget too large for an Instant, like u64::MAX, means no deadline at all
+    let deadline = Instant::now().checked_add(Duration::from_millis(max_millis));
+    let mut board = board.clone();
+    let mut limits = Limits::unlimited();
+    let mut res = None;
+    let mut previous_best = None;
+
+    for depth in 1..=MAX_DEPTH {
+        let best_move = match search_root(&mut board, depth, previous_best, &mut limits) {
+            Some(best_move) if !limits.is_stopped => best_move,
+            _ => break,
+        };
+        previous_best = Some(best_move);
+        res = Some(((best_move.0, best_move.1), depth));
+
+        limits.deadline = deadline;
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+    }
+
+    res
+}
+
+// find_best_move_timed that plays from the book while the game is in the opening and the
+// position is in it, a book move comes back with depth 0
+#[cfg(feature = "rand")]
+pub fn find_best_move_timed_with_book(board: &Board, max_millis: u64, book: &OpeningBook, rng: &mut impl rand::Rng) -> Option<((Address, Address), u32)> {
+    if game_engine::detect_phase(board) == GamePhase::Opening {
+        if let Some(mv) = book.probe(board, rng) {
+            return Some(((mv.from, mv.to), 0));
+        }
+    }
+
+    find_best_move_timed(board, max_millis)
+}
+
+// the previous best move, when given, is searched first
+fn search_root(board: &mut Board, depth: u32, previous_best: Option<(Address, Address, PieceType)>, limits: &mut Limits) -> Option<(Address, Address, PieceType)> {
+    let mut best_move = None;
+    let mut alpha = -MATE_SCORE - 1;
+    let beta = MATE_SCORE + 1;
+
+    let mut moves = game_engine::get_all_legal_moves_promoting(board);
+    order_moves(board, &mut moves);
+    if let Some(index) = moves.iter().position(|mv| Some(*mv) == previous_best) {
+        moves[..=index].rotate_right(1);
+    }
+
+    for (from, to, promotion) in moves {
+        game_engine::make_move_unchecked_promoting(board, from, to, promotion);
+        let score = -negamax(board, depth.saturating_sub(1), 1, -beta, -alpha, limits);
+        board.unmake_move();
+
+        if limits.is_stopped {
+            break;
+        }
+
+        if best_move.is_none() || score > alpha {
+            alpha = score;
+            best_move = Some((from, to, promotion));
+        }
+    }
+
+    best_move
+}
+
+fn negamax(board: &mut Board, depth: u32, ply: i32, mut alpha: i32, beta: i32, limits: &mut Limits) -> i32 {
+    if limits.should_stop() {
+        return 0;
+    }
+
+    let mut moves = game_engine::get_all_legal_moves_promoting(board);
+
+    if moves.is_empty() {
+        return if game_engine::is_in_check(board, board.whose_turn) {
+            -MATE_SCORE + ply
+        } else {
+            0
+        };
+    }
+
+    if depth == 0 {
+        let score = evaluation::evaluate(board);
+        return if board.whose_turn == Color::White { score } else { -score };
+    }
+
+    order_moves(board, &mut moves);
+
+    for (from, to, promotion) in moves {
+        game_engine::make_move_unchecked_promoting(board, from, to, promotion);
+        let score = -negamax(board, depth - 1, ply + 1, -beta, -alpha, limits);
+        board.unmake_move();
+
+        if limits.is_stopped {
+            return 0;
+        }
+
+        if score >= beta {
+            return beta;
+        }
+        alpha = alpha.max(score);
+    }
+
+    alpha
+}
+
+// shortest forced mate for the side to move within max_moves of its own moves,
+// the line alternates mating moves with the defence that holds out the longest
+pub fn find_mate(board: &Board, max_moves: u32) -> Option<Vec<Move>> {
+    let mut board = board.clone();
+    (1..=max_moves).find_map(|moves| mate_in(&mut board, moves))
+}
+
+// a line mating in at most `moves` moves, or None: every defence has to lose,
+// so a single reply that escapes cuts the whole branch
+fn mate_in(board: &mut Board, moves: u32) -> Option<Vec<Move>> {
+    let defender = board.whose_turn.opposite();
+
+    for mv in game_engine::get_all_legal_moves_detailed(board) {
+        play(board, mv);
+        let line = if game_engine::is_checkmate(board, defender) {
+            Some(Vec::new())
+        } else if moves > 1 {
+            longest_defence(board, moves - 1)
+        } else {
+            None
+        };
+        board.unmake_move();
+
+        if let Some(mut line) = line {
+            line.insert(0, mv);
+            return Some(line);
+        }
+    }
+
+    None
+}
+
+// the reply after which the mate takes the longest with the line that follows it,
+// None when some reply escapes a mate within `moves` or there is no reply at all
+fn longest_defence(board: &mut Board, moves: u32) -> Option<Vec<Move>> {
+    let mut res: Option<Vec<Move>> = None;
+
+    for reply in game_engine::get_all_legal_moves_detailed(board) {
+        play(board, reply);
+        let line = (1..=moves).find_map(|moves| mate_in(board, moves));
+        board.unmake_move();
+
+        let mut line = line?;
+        line.insert(0, reply);
+        if res.as_ref().is_none_or(|longest| line.len() > longest.len()) {
+            res = Some(line);
+        }
+    }
+
+    res
+}
+
+fn play(board: &mut Board, mv: Move) {
+    game_engine::make_move_unchecked_promoting(board, mv.from, mv.to, mv.promotion().unwrap_or(PieceType::Queen));
+}
+
+// MVV-LVA: captures first, the most valuable victim taken by the least valuable
+// attacker leading, then promotions, then quiet moves in their generated order
+pub fn order_moves(board: &Board, moves: &mut [(Address, Address, PieceType)]) {
+    static CAPTURE_BONUS: u32 = 1_000_000;
+    // the king attacks as if it were worth a little more than a queen
+    static KING_ATTACKER_VALUE: u32 = 1000;
+
+    let value = |piece_type: PieceType| match piece_type {
+        PieceType::King => KING_ATTACKER_VALUE,
+        _ => piece_type.get_value(GamePhase::Middlegame),
+    };
+
+    let score = |(from, to, promotion): &(Address, Address, PieceType)| -> u32 {
+        let attacker = match board.get_cell(*from) {
+            Some(piece) => *piece,
+            None => return 0,
+        };
+
+        let victim = match board.get_cell(*to) {
+            Some(piece) if piece.color != attacker.color => Some(piece.piece_type),
+            // a chess960 king castles onto its own rook
+            Some(_) => None,
+            // only a pawn moves diagonally onto an empty square, and that is en passant
+            None if attacker.piece_type == PieceType::Pawn && from.col != to.col => Some(PieceType::Pawn),
+            None => None,
+        };
+
+        let mut res = match victim {
+            Some(victim) => CAPTURE_BONUS + value(victim) * 10 - value(attacker.piece_type),
+            None => 0,
+        };
+
+        if game_engine::is_promotion_move(board, *from, *to) {
+            res += value(*promotion);
+        }
+        res
+    };
+
+    moves.sort_by_key(|m| std::cmp::Reverse(score(m)));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn best(fen: &str, depth: u32) -> Option<(String, String)> {
+        let board = Board::from_fen(fen).unwrap();
+        find_best_move(&board, depth).map(|(from, to)| (from.to_string(), to.to_string()))
+    }
+
+    fn mv(from: &str, to: &str) -> Option<(String, String)> {
+        Some((from.to_string(), to.to_string()))
+    }
+
+    #[test]
+    fn search_finds_mate_in_one() {
+        assert_eq!(best("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2), mv("a1", "a8"));
+        assert_eq!(best("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1", 3), mv("a8", "a1"));
+    }
+
+    #[test]
+    fn search_takes_free_material() {
+        assert_eq!(best("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2), mv("d2", "d5"));
+    }
+
+    #[test]
+    fn search_terminal_positions() {
+        // mated and stalemated sides have nothing to play
+        assert_eq!(best("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 2), None);
+        assert_eq!(best("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 2), None);
+    }
+
+    #[test]
+    fn search_prefers_shorter_mate() {
+        // Qg7 mates at once, while other queen moves mate later at best
+        assert_eq!(best("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1", 3), mv("g1", "g7"));
+    }
+
+    #[test]
+    fn search_under_promotes() {
+        let addr = Address::parse;
+
+        // only the knight mates, a queen on f8 does not even give check
+        let board = Board::from_fen("6br/5Ppk/6pp/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(find_best_move_promoting(&board, 2), Some((addr("f7"), addr("f8"), PieceType::Knight)));
+        assert_eq!(find_mate(&board, 1).unwrap()[0].promotion(), Some(PieceType::Knight));
+
+        let board = board.mirror_vertical();
+        assert_eq!(find_best_move_promoting(&board, 2), Some((addr("f2"), addr("f1"), PieceType::Knight)));
+
+        // without a mate around the queen is still the piece to take
+        let board = Board::from_fen("8/P5k1/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(find_best_move_promoting(&board, 2), Some((addr("a7"), addr("a8"), PieceType::Queen)));
+    }
+
+    #[test]
+    fn parallel_search_matches_sequential() {
+        let positions = [
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3),
+            ("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3),
+            ("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1", 3),
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 2),
+        ];
+
+        for (fen, depth) in positions {
+            let board = Board::from_fen(fen).unwrap();
+            let expected = find_best_move(&board, depth);
+            for threads in [0, 1, 2, 4] {
+                assert_eq!(find_best_move_parallel(&board, depth, threads), expected, "{} with {} threads", fen, threads);
+            }
+        }
+
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(find_best_move_parallel(&board, 2, 4), None);
+    }
+
+    #[test]
+    fn search_finishes_rook_ending() {
+        // the depth is far too low to see a mate, the mate table plays it
+        let mut board = Board::from_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        for _ in 0..14 {
+            let (from, to) = find_best_move(&board, 1).unwrap();
+            game_engine::make_move_coords(&mut board, from, to).unwrap();
+            if game_engine::is_checkmate(&board, Color::Black) {
+                return;
+            }
+
+            let (from, to) = find_best_move(&board, 1).unwrap();
+            game_engine::make_move_coords(&mut board, from, to).unwrap();
+        }
+        panic!("no mate in {}", board.to_fen());
+    }
+
+    #[test]
+    fn move_ordering() {
+        let addr = Address::parse;
+        // the queen can be taken by the pawn or the rook, the knight only by the rook
+        let board = Board::from_fen("4k3/8/8/2q1n3/1P6/8/8/2R1K3 w - - 0 1").unwrap();
+        let mut moves = game_engine::get_all_legal_moves_promoting(&board);
+        order_moves(&board, &mut moves);
+
+        let first = moves.iter().take(2).map(|(from, to, _)| (*from, *to)).collect::<Vec<_>>();
+        assert_eq!(first, vec![(addr("b4"), addr("c5")), (addr("c1"), addr("c5"))]);
+
+        let is_capture = |(_, to, _): &(Address, Address, PieceType)| board.has_piece(*to);
+        let captures = moves.iter().take_while(|m| is_capture(m)).count();
+        assert!(moves[captures..].iter().all(|m| !is_capture(m)));
+
+        // a quiet promotion comes after captures but before quiet moves, queen first
+        let board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut moves = game_engine::get_all_legal_moves_promoting(&board);
+        order_moves(&board, &mut moves);
+        assert_eq!(moves[0], (addr("a7"), addr("a8"), PieceType::Queen));
+        assert_eq!(moves[3], (addr("a7"), addr("a8"), PieceType::Knight));
+    }
+
+    #[test]
+    fn mate_finder() {
+        let line = |fen: &str, max_moves: u32| -> Option<Vec<String>> {
+            let board = Board::from_fen(fen).unwrap();
+            find_mate(&board, max_moves).map(|line| line.iter().map(|mv| mv.to_string()).collect())
+        };
+
+        assert_eq!(line("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3), Some(vec!["a1a8".to_string()]));
+
+        // rook roller: Rb7 leaves the king only g8, then Ra8 mates
+        let fen = "7k/8/8/8/8/8/R7/1R4K1 w - - 0 1";
+        assert_eq!(line(fen, 1), None);
+        let mate = line(fen, 2).unwrap();
+        assert_eq!(mate.len(), 3);
+        assert_eq!(mate[1], "h8g8");
+
+        let mut board = Board::from_fen(fen).unwrap();
+        for mv in find_mate(&board.clone(), 3).unwrap() {
+            play(&mut board, mv);
+        }
+        assert!(game_engine::is_checkmate(&board, Color::Black));
+
+        // nothing is forced from the start or with a lone king
+        assert_eq!(line(&Board::new().to_fen(), 2), None);
+        assert_eq!(line("7k/8/8/8/8/8/8/6K1 w - - 0 1", 2), None);
+    }
+
+    #[test]
+    fn timed_search() {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (best_move, depth) = find_best_move_timed(&board, 50).unwrap();
+        assert_eq!(best_move, (Address::parse("a1"), Address::parse("a8")));
+        assert!(depth >= 1);
+
+        // even without any time the first depth is completed
+        let (_, depth) = find_best_move_timed(&Board::new(), 0).unwrap();
+        assert_eq!(depth, 1);
+
+        // the budget is kept roughly even in the middlegame
+        let board = Board::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8").unwrap();
+        let start = Instant::now();
+        let (_, depth) = find_best_move_timed(&board, 200).unwrap();
+        assert!(depth >= 1);
+        assert!(start.elapsed() < Duration::from_millis(2000), "took {:?}", start.elapsed());
+
+        assert_eq!(find_best_move_timed(&Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap(), 50), None);
+        // an endless budget does not overflow the clock
+        assert_eq!(find_best_move_timed(&Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap(), u64::MAX), None);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn timed_search_with_book() {
+        use rand::SeedableRng;
+
+        let book = OpeningBook::parse("\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 => d2d4
+6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1 => a1a2
+").unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+        let (best_move, depth) = find_best_move_timed_with_book(&Board::new(), 10, &book, &mut rng).unwrap();
+        assert_eq!((best_move, depth), ((Address::parse("d2"), Address::parse("d4")), 0));
+
+        // out of the book the search takes over
+        let mut board = Board::new();
+        board.apply_uci_moves(&["e2e4"]).unwrap();
+        let (_, depth) = find_best_move_timed_with_book(&board, 10, &book, &mut rng).unwrap();
+        assert!(depth >= 1);
+
+        // past the opening the book is not asked
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (best_move, depth) = find_best_move_timed_with_book(&board, 50, &book, &mut rng).unwrap();
+        assert_eq!(best_move, (Address::parse("a1"), Address::parse("a8")));
+        assert!(depth >= 1);
+    }
+
+    // the time bound holds for release builds, a debug build is many times slower
+    #[test]
+    #[cfg_attr(debug_assertions, ignore)]
+    fn search_start_position() {
+        let start = Instant::now();
+        let res = find_best_move(&Board::new(), 3);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(res.is_some());
+    }
+}
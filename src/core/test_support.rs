@@ -0,0 +1,33 @@
+// shorthands for positional tests, compiled into the test build only
+
+use super::chess_types::*;
+use super::game_engine;
+
+// SAN of every legal move in the position, sorted
+pub(crate) fn legal_sans(fen: &str) -> Vec<String> {
+    let board = Board::from_fen(fen).unwrap_or_else(|err| panic!("bad FEN {}: {:?}", fen, err));
+
+    let mut res = game_engine::get_all_legal_moves_detailed(&board).iter()
+        .map(|mv| game_engine::move_to_san_promoting(&board, mv.from, mv.to, mv.promotion().unwrap_or(PieceType::Queen)))
+        .collect::<Vec<_>>();
+    res.sort();
+    res
+}
+
+// checks the legal moves of a FEN position, either all of them as SAN in any order
+// or just how many there are:
+//
+//   assert_moves!("4k3/8/8/8/8/8/8/4K3 w - - 0 1", ["Kd1", "Kd2", "Ke2", "Kf2", "Kf1"]);
+//   assert_moves!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 20);
+macro_rules! assert_moves {
+    ($fen:expr, [$($san:expr),* $(,)?]) => {{
+        let mut expected: Vec<String> = vec![$($san.to_string()),*];
+        expected.sort();
+        assert_eq!($crate::core::test_support::legal_sans($fen), expected, "legal moves of {}", $fen);
+    }};
+    ($fen:expr, $count:expr) => {
+        assert_eq!($crate::core::test_support::legal_sans($fen).len(), $count, "legal move count of {}", $fen);
+    };
+}
+
+pub(crate) use assert_moves;
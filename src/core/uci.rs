@@ -0,0 +1,78 @@
+use super::chess_types::*;
+use super::game_engine;
+use super::search;
+
+// "position" command arguments like "startpos moves e2e4 e7e5" or "fen <FEN> moves ...",
+// an invalid FEN falls back to the start position and moves stop at the first illegal one
+pub fn parse_position(cmd: &str) -> Board {
+    let cmd = cmd.trim();
+    let cmd = cmd.strip_prefix("position").unwrap_or(cmd).trim();
+
+    let (setup, moves) = match cmd.split_once("moves") {
+        Some((setup, moves)) => (setup.trim(), moves),
+        None => (cmd, ""),
+    };
+
+    let mut board = match setup.strip_prefix("fen") {
+        Some(fen) => Board::from_fen(fen.trim()).unwrap_or_else(|_| Board::new()),
+        None => Board::new(),
+    };
+
+    // the moves up to the bad one are kept, which is all the command can give
+    let _ = board.apply_uci_moves(&moves.split_whitespace().collect::<Vec<_>>());
+
+    board
+}
+
+// best move in long algebraic notation, "0000" when there is none
+pub fn best_move_uci(board: &Board, depth: u32) -> String {
+    match search::find_best_move_promoting(board, depth) {
+        Some((from, to, promotion)) => {
+            let promotion = game_engine::is_promotion_move(board, from, to).then_some(promotion);
+            Move::new(from, to, promotion).to_string()
+        },
+        None => "0000".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn position_startpos() {
+        assert_eq!(parse_position("position startpos").to_fen(), Board::new().to_fen());
+
+        let board = parse_position("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        // stops at the illegal e5e4
+        let board = parse_position("startpos moves e2e4 e7e5 e5e4 g1f3");
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    }
+
+    #[test]
+    fn position_fen() {
+        let board = parse_position("position fen 4k3/1P6/8/8/8/8/8/4K3 w - - 0 1 moves b7b8n e8e7");
+        assert_eq!(board.to_fen(), "1N6/4k3/8/8/8/8/8/4K3 w - - 1 2");
+
+        let board = parse_position("position fen 4k3/8/8/8/8/8/8/4K3 b - - 5 9");
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 5 9");
+    }
+
+    #[test]
+    fn best_move() {
+        let board = parse_position("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(best_move_uci(&board, 2), "a1a8");
+
+        let board = parse_position("position fen 8/P5k1/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(best_move_uci(&board, 2), "a7a8q");
+
+        // under-promotion with mate
+        let board = parse_position("position fen 6br/5Ppk/6pp/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(best_move_uci(&board, 2), "f7f8n");
+
+        let board = parse_position("position fen R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        assert_eq!(best_move_uci(&board, 2), "0000");
+    }
+}
@@ -0,0 +1,115 @@
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+
+use super::chess_types::*;
+use super::game_engine::{self, DrawReason, GameStatus};
+
+// browser facing wrapper around a Board, addresses go both ways as strings like "e2"
+// and errors as their description
+#[wasm_bindgen]
+pub struct JsBoard {
+    board: Board,
+}
+
+impl Default for JsBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl JsBoard {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsBoard {
+        JsBoard { board: Board::new() }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<JsBoard, String> {
+        Board::from_fen(fen)
+            .map(|board| JsBoard { board })
+            .map_err(|err| format!("{:?}", err))
+    }
+
+    // legal destinations of the piece on the square, empty for an empty or invalid square
+    pub fn legal_moves(&self, square: &str) -> Vec<String> {
+        let address = match Address::from_str(square) {
+            Ok(address) => address,
+            Err(_) => return Vec::new(),
+        };
+
+        game_engine::get_legal_moves(&self.board, address)
+            .map(|moves| moves.iter().map(|to| to.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    // `promotion` is a piece letter like "n", a queen is taken when it is missing
+    pub fn play(&mut self, from: &str, to: &str, promotion: Option<String>) -> Result<(), String> {
+        let parse = |square: &str| Address::from_str(square).map_err(|_| format!("invalid square {}", square));
+        let from = parse(from)?;
+        let to = parse(to)?;
+
+        let promotion = match promotion.as_deref().map(|s| s.chars().collect::<Vec<_>>()).as_deref() {
+            None | Some([]) => None,
+            Some([ch]) => Some(PieceType::from_char(*ch).ok_or(format!("invalid promotion {}", ch))?),
+            Some(_) => return Err(format!("invalid promotion {}", promotion.unwrap_or_default())),
+        };
+
+        game_engine::make_move(&mut self.board, Move::new(from, to, promotion))
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn fen(&self) -> String {
+        self.board.to_fen()
+    }
+
+    // snake case name like "check", "checkmate" or "stalemate"
+    pub fn status(&self) -> String {
+        match game_engine::game_status(&self.board) {
+            GameStatus::Ongoing                              => "ongoing",
+            GameStatus::Check                                => "check",
+            GameStatus::Checkmate                            => "checkmate",
+            GameStatus::Draw(DrawReason::Stalemate)          => "stalemate",
+            GameStatus::Draw(DrawReason::SeventyFiveMove)    => "seventy_five_move",
+            // a lone board never reports these, they are only here to be exhaustive
+            GameStatus::Draw(DrawReason::FiftyMove)          => "fifty_move",
+            GameStatus::Draw(DrawReason::Repetition)         => "repetition",
+            GameStatus::Draw(DrawReason::FivefoldRepetition) => "fivefold_repetition",
+            GameStatus::Resignation(_)                       => "resignation",
+            GameStatus::AgreedDraw                           => "agreed_draw",
+        }.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn js_board_play() {
+        let mut board = JsBoard::new();
+        assert_eq!(board.legal_moves("e2"), vec!["e3", "e4"]);
+        assert!(board.legal_moves("e4").is_empty());
+        assert!(board.legal_moves("z9").is_empty());
+
+        assert_eq!(board.play("e2", "e4", None), Ok(()));
+        assert_eq!(board.play("e4", "e5", None), Err("it is Black's turn".to_string()));
+        assert_eq!(board.play("e9", "e5", None), Err("invalid square e9".to_string()));
+        assert_eq!(board.fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!(board.status(), "ongoing");
+    }
+
+    #[test]
+    fn js_board_promotion_and_status() {
+        let mut board = JsBoard::from_fen("7k/1P6/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(board.play("b7", "b8", Some("x".to_string())), Err("invalid promotion x".to_string()));
+        assert_eq!(board.play("b7", "b8", Some("k".to_string())), Err("a pawn cannot promote to a king".to_string()));
+        assert_eq!(board.play("b7", "b8", Some("q".to_string())), Ok(()));
+        assert_eq!(board.status(), "checkmate");
+
+        let board = JsBoard::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.status(), "stalemate");
+
+        assert!(JsBoard::from_fen("8/8 w - - 0 1").is_err());
+    }
+}
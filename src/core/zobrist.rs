@@ -0,0 +1,222 @@
+#[cfg(test)]
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::chess_types::*;
+#[cfg(test)]
+use super::game_engine;
+
+static ZOBRIST_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+// random keys XOR-ed together to get a position hash,
+// the tables are generated from a fixed seed so hashes are stable between runs
+pub struct ZobristKeys {
+    pieces: [[u64; CELLS_COUNT as usize]; 12],
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant: [u64; ROW_SIZE as usize],
+}
+
+impl ZobristKeys {
+    fn generate(seed: u64) -> Self {
+        // splitmix64
+        let mut state = seed;
+        let mut next = || -> u64 {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        let mut keys = ZobristKeys {
+            pieces: [[0; CELLS_COUNT as usize]; 12],
+            black_to_move: 0,
+            castling: [0; 4],
+            en_passant: [0; ROW_SIZE as usize],
+        };
+
+        for table in keys.pieces.iter_mut() {
+            for key in table.iter_mut() {
+                *key = next();
+            }
+        }
+        keys.black_to_move = next();
+        for key in keys.castling.iter_mut() {
+            *key = next();
+        }
+        for key in keys.en_passant.iter_mut() {
+            *key = next();
+        }
+
+        keys
+    }
+
+    pub fn get() -> &'static ZobristKeys {
+        static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+        KEYS.get_or_init(|| Self::generate(ZOBRIST_SEED))
+    }
+
+    // the hooks below are what an incremental update toggles on each change
+
+    pub fn piece(&self, address: Address, piece: Piece) -> u64 {
+        let color_offset = if piece.color == Color::White { 0 } else { 6 };
+        self.pieces[color_offset + piece.piece_type as usize][address.to_index() as usize]
+    }
+
+    pub fn side(&self, color: Color) -> u64 {
+        if color == Color::Black { self.black_to_move } else { 0 }
+    }
+
+    pub fn castling(&self, right: usize) -> u64 {
+        self.castling[right]
+    }
+
+    pub fn en_passant(&self, col: u8) -> u64 {
+        self.en_passant[col as usize]
+    }
+}
+
+// two different positions with the same zobrist hash among all the positions perft
+// reaches from the board up to the depth, None when every hash is unique
+#[cfg(test)]
+pub(crate) fn audit_hash_collisions(board: &Board, depth: u32) -> Option<(Board, Board)> {
+    get_collision(&mut board.clone(), depth, &Board::zobrist_hash, &mut HashMap::new())
+}
+
+#[cfg(test)]
+fn get_collision(board: &mut Board, depth: u32, hash: &impl Fn(&Board) -> u64, seen: &mut HashMap<u64, Board>) -> Option<(Board, Board)> {
+    let position = get_hashed_position(board);
+    match seen.get(&hash(board)) {
+        Some(other) if *other != position => return Some((other.clone(), position)),
+        Some(_)                           => {},
+        None                              => { seen.insert(hash(board), position); },
+    }
+
+    if depth == 0 {
+        return None;
+    }
+
+    for (from, to, promotion) in game_engine::get_all_legal_moves_promoting(board) {
+        game_engine::make_move_unchecked_promoting(board, from, to, promotion);
+        let res = get_collision(board, depth - 1, hash, seen);
+        board.unmake_move();
+        if res.is_some() {
+            return res;
+        }
+    }
+
+    None
+}
+
+// the board without what the hash leaves out on purpose: the move counters, the history
+// and an en passant target no pawn can take on
+#[cfg(test)]
+fn get_hashed_position(board: &Board) -> Board {
+    let mut res = board.clone();
+    res.halfmove_clock = 0;
+    res.fullmove_number = 1;
+    res.history.clear();
+    res.redo_history.clear();
+    res.en_passant_target = board.en_passant_target.filter(|target| board.can_capture_en_passant(*target));
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::game_engine;
+
+    #[test]
+    fn keys_are_distinct() {
+        let keys = ZobristKeys::get();
+        let mut all = keys.pieces.iter().flatten().copied().collect::<Vec<_>>();
+        all.push(keys.black_to_move);
+        all.extend(keys.castling);
+        all.extend(keys.en_passant);
+
+        let count = all.len();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), count);
+    }
+
+    #[test]
+    fn transpositions_hash_equal() {
+        let mut board_a = Board::new();
+        let res = game_engine::make_moves(&mut board_a, vec![
+            ("g1", "f3"),
+            ("g8", "f6"),
+            ("b1", "c3"),
+            ("b8", "c6"),
+        ]);
+        assert!(res.is_ok());
+
+        let mut board_b = Board::new();
+        let res = game_engine::make_moves(&mut board_b, vec![
+            ("b1", "c3"),
+            ("b8", "c6"),
+            ("g1", "f3"),
+            ("g8", "f6"),
+        ]);
+        assert!(res.is_ok());
+
+        assert_eq!(board_a.zobrist_hash(), board_b.zobrist_hash());
+        assert_ne!(board_a.zobrist_hash(), Board::new().zobrist_hash());
+
+        // the long march played last leaves no capturable pawn behind
+        let mut board_c = Board::new();
+        let res = game_engine::make_moves(&mut board_c, vec![
+            ("e2", "e4"),
+            ("g8", "f6"),
+            ("g1", "f3"),
+        ]);
+        assert!(res.is_ok());
+
+        let mut board_d = Board::new();
+        let res = game_engine::make_moves(&mut board_d, vec![
+            ("g1", "f3"),
+            ("g8", "f6"),
+            ("e2", "e4"),
+        ]);
+        assert!(res.is_ok());
+        assert_eq!(board_d.en_passant_target, Some(Address::parse("e3")));
+        assert_eq!(board_c.zobrist_hash(), board_d.zobrist_hash());
+    }
+
+    #[test]
+    fn hash_state_sensitive() {
+        let plain = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        let castling = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let black = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b - - 0 1").unwrap();
+
+        assert_ne!(plain.zobrist_hash(), castling.zobrist_hash());
+        assert_ne!(plain.zobrist_hash(), black.zobrist_hash());
+
+        // en passant only matters when the capture is actually available
+        let with_ep = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let without_ep = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_ne!(with_ep.zobrist_hash(), without_ep.zobrist_hash());
+
+        let with_ep = Board::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1").unwrap();
+        let without_ep = Board::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(with_ep.zobrist_hash(), without_ep.zobrist_hash());
+    }
+
+    #[test]
+    fn no_hash_collisions() {
+        assert!(audit_hash_collisions(&Board::new(), 3).is_none());
+
+        let kiwipete = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert!(audit_hash_collisions(&kiwipete, 2).is_none());
+
+        let en_passant = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert!(audit_hash_collisions(&en_passant, 4).is_none());
+
+        // a hash blind to the side to move is caught at once
+        let blind = |board: &Board| board.zobrist_hash() ^ ZobristKeys::get().side(board.whose_turn);
+        let (a, b) = get_collision(&mut Board::new(), 4, &blind, &mut HashMap::new()).unwrap();
+        assert!(a.whose_turn != b.whose_turn);
+        assert!(a.to_fen() != b.to_fen());
+    }
+}
@@ -1,31 +1,26 @@
-mod core;
-use crate::core::chess_types::*;
-use crate::core::*;
+use chest::core::chess_types::*;
+use chest::core::*;
 
 fn main() {
     let mut board = Board::new();
-    //game_engine::make_move(&mut board, Address::parse("e2"), Address::parse("e4"));
 
-    let _ = game_engine::make_moves(&mut board, vec![
-        ("e2", "e4"),
-        ("c7", "c6"),
-        ("b1", "c3"),
-        ("d7", "d5"),
-        ("e4", "d5"),
-        ("d5", "c6"),
-        ("c6", "c7"),
-        ("c7", "d8"),
-        ("e8", "d8"),
-        ("c8", "h3"),
-        ("g2", "h3"),
-        ("h3", "g4"),
-    ]);
+    let moves = [
+        "e2e4", "c7c6",
+        "b1c3", "d7d5",
+        "e4d5", "d5c6",
+        "c6c7", "c7d8",
+        "e8d8", "c8h3",
+        "g2h3", "h3g4",
+    ];
+    if let Err(error) = board.apply_uci_moves(&moves) {
+        println!("{:?}", error);
+    }
 
     println!("{}", board);
 
     let res
         = game_engine::get_piece_moves(&board, Address::parse("e1"));
-    assert_eq!(res.is_ok(), true);
+    assert!(res.is_ok());
 
     for addr in res.unwrap() {
         print!("{} ", addr);